@@ -0,0 +1,64 @@
+#!/usr/bin/env python3
+"""generate src/compiler/parser/casefold.rs, the full case folding table
+
+usage: scripts/casefold.py [CaseFolding.txt] > src/compiler/parser/casefold.rs
+
+with a CaseFolding.txt of the unicode character database the entries of
+status C and F are taken, else the table of `str.casefold`, which is the same
+C + F folding for the unicode version of the python interpreter
+"""
+
+import sys
+import unicodedata
+
+
+def from_file(path):
+    version = None
+    table = {}
+    with open(path, encoding="utf-8") as f:
+        for line in f:
+            if version is None and line.startswith("# CaseFolding-"):
+                version = line[len("# CaseFolding-"):].split(".txt")[0]
+            line = line.split("#", 1)[0].strip()
+            if not line:
+                continue
+            code, status, mapping = [x.strip() for x in line.split(";")[:3]]
+            if status in ("C", "F"):
+                table[int(code, 16)] = "".join(chr(int(x, 16)) for x in mapping.split())
+    return version, table
+
+
+def from_python():
+    table = {}
+    for code in range(0x110000):
+        x = chr(code)
+        if 0xD800 <= code < 0xE000:
+            continue
+        if x.casefold() != x:
+            table[code] = x.casefold()
+    return unicodedata.unidata_version, table
+
+
+def escape(text):
+    return "".join("\\u{%x}" % ord(x) for x in text)
+
+
+def main():
+    if len(sys.argv) > 1:
+        version, table = from_file(sys.argv[1])
+        source = "CaseFolding.txt, status C and F"
+    else:
+        version, table = from_python()
+        source = "`str.casefold` of python, the C and F entries of CaseFolding.txt"
+    print("// generated by scripts/casefold.py, do not edit by hand")
+    print("// from %s, unicode %s" % (source, version))
+    print()
+    print("/// full case folding of the characters which do not fold to themselves,")
+    print("/// sorted by character")
+    print("pub(super) static FOLDING: &[(char, &str)] = &[")
+    for code in sorted(table):
+        print('    (\'%s\', "%s"),' % (escape(chr(code)), escape(table[code])))
+    print("];")
+
+
+main()
@@ -1,87 +1,741 @@
 use super::{
-    ast::{BinaryExpr, Expr, FunctionExpr},
+    ast::{BinaryExpr, Expr, Float, FunctionExpr, InterpolationExpr, Program, Span, TemplatePart},
+    cst,
     parser::{
-        combinators::{self},
-        parsec::{self, between, character, token, tokens},
+        grammar::Tree,
+        parseco::{self, err, err_at, Locator, ParserError},
     },
-    tokenizer::{valid_string_content, identifier},
+    tex::{Diagnostic, Severity, TouchMeToken, TouchMeTokenBundle},
+    tokenizer::{normalize, number, template_literal, Number, Segment},
 };
 
-/// if cannot find value (`identifier`) in this scope then parse it as string
-pub fn primary_expr(input: &str) -> Result<(&str, Expr), parsec::ParseError> {
+/// match a raw or a template string at byte `position` and at `locator` of
+/// the source, the expressions embedded in it are parsed by `parse_at`
+///
+/// e.g. `"total: ${a + b}"` is an `Expr::Interpolation`, `"total"` is an
+/// `Expr::String`
+pub fn quoted(text: &str, position: usize, locator: Locator) -> Result<Expr, ParserError> {
+    let segments = match template_literal(text) {
+        Ok((_, x)) => x,
+        Err(e) => return err_at(&format!("expected {}", e.expected), locator),
+    };
+    let mut parts = Vec::new();
+    for segment in segments {
+        match segment {
+            Segment::Text(x) => parts.push(TemplatePart::Text(x)),
+            Segment::Code(code) => {
+                let offset = code.as_ptr() as usize - text.as_ptr() as usize;
+                let mut state = parseco::state(&text[..offset]);
+                state.locator = locator;
+                while state.next().is_some() {}
+                let x = parse_at(code, position + offset, state.locator)?;
+                parts.push(TemplatePart::Expr(x));
+            }
+        }
+    }
+    match parts.as_mut_slice() {
+        [TemplatePart::Text(x)] => Ok(Expr::String(std::mem::take(x))),
+        _ => Ok(Expr::Interpolation(Box::new(InterpolationExpr { parts }))),
+    }
+}
 
-    let paren = 
-    // parsec::of(expr)
-    // .between(character('('), character(')'));
-    
-    between(character('('), character(')'), expr);
+/// apply `f` to the span of every parentheses in `expr`
+fn map_spans(expr: &mut Expr, f: &impl Fn(Span) -> Span) {
+    if let Expr::Paren(x) = expr {
+        x.span = f(x.span);
+    }
+    for x in expr.children_mut() {
+        map_spans(x, f);
+    }
+}
 
+/// tokens of `bundle` in the trivia mode and the warnings of the lexer, or
+/// its first error
+fn tokens(bundle: TouchMeTokenBundle) -> Result<(Vec<TouchMeToken>, Vec<Diagnostic>), ParserError> {
+    let mut bundle = bundle.with_trivia();
+    let tokens: Vec<_> = bundle.by_ref().collect();
+    let (errors, warnings): (Vec<_>, Vec<_>) = bundle
+        .diagnostics()
+        .iter()
+        .cloned()
+        .partition(|x| x.severity == Severity::Error);
+    match errors.first() {
+        Some(x) => {
+            let locator = Locator::new(x.columno as usize - 1, x.lineno as usize - 1);
+            err_at(&x.message, locator)
+        }
+        None => Ok((tokens, warnings)),
+    }
+}
 
-    
-    let identifier = parsec::map(identifier, |x| Expr::Identifier(x));
-    let number = parsec::map(token(|x| x.is_digit(10)), |x| {
-        Expr::Integer(x.parse::<i64>().unwrap())
-    });
-    let as_string = parsec::map(valid_string_content, |x| Expr::String(x));
+/// parse the whole `source` as an expression
+///
+/// `source` is lexed in the layout mode and parsed into a concrete syntax
+/// tree by `cst`, which is then lowered
+pub fn parse(source: &str) -> Result<Expr, ParserError> {
+    parse_with_warnings(source).map(|(x, _)| x)
+}
 
-    parsec::either4(paren, identifier, number, as_string)(input)
+/// `parse`, and the warnings of the lexer about `source`, e.g. that the `-`
+/// of `f -1` may not be the minus meant
+pub fn parse_with_warnings(source: &str) -> Result<(Expr, Vec<Diagnostic>), ParserError> {
+    let (tokens, warnings) = tokens(TouchMeTokenBundle::new(source, "").with_layout())?;
+    Ok((cst::lower(&cst::parse_tokens(&tokens))?, warnings))
 }
 
-pub fn add_expr(input: &str) -> Result<(&str, Expr), parsec::ParseError> {
-    let add_infix = combinators::leak(character('+'));
-    let parser = parsec::follow(parsec::follow(primary_expr, add_infix), primary_expr);
-    let morph = |x: ((Expr, String), Expr)| {
-        Expr::Add(Box::new(BinaryExpr {
-            operator: x.0 .1,
-            lhs: x.0 .0,
-            rhs: x.1,
-        }))
-    };
-    parsec::map(parser, morph)(input)
+/// `parse` of `source`, which is the text at byte `position` and at
+/// `locator` of an enclosing source, e.g. the code embedded in a string,
+/// in which line breaks are whitespace as in brackets
+pub fn parse_at(source: &str, position: usize, locator: Locator) -> Result<Expr, ParserError> {
+    let bundle = TouchMeTokenBundle::new(source, "").with_start(position, locator);
+    let (tokens, _) = tokens(bundle)?;
+    cst::lower(&cst::parse_tokens(&tokens))
 }
 
-pub fn expr(input: &str) -> Result<(&str, Expr), parsec::ParseError> {
-    parsec::either(add_expr, primary_expr)(input)
+/// `parse` of a whole source file, whose items are definitions or
+/// expressions separated by `;` or by line breaks, a line goes on with the
+/// lines indented after it
+///
+/// e.g.
+/// ```text
+/// def square x = x * x
+/// let y = square 3; y + 1
+/// ```
+pub fn parse_program(source: &str) -> Result<Program, ParserError> {
+    let (tokens, _) = tokens(TouchMeTokenBundle::new(source, "").with_layout())?;
+    cst::lower_program(&cst::parse_tokens(&tokens))
 }
 
+pub fn number_expr(x: Number) -> Expr {
+    match x {
+        Number::Integer(x) => Expr::Integer(x),
+        Number::BigInteger(x) => Expr::BigInteger(x),
+        Number::Float(x) => Expr::Float(Float(x)),
+    }
+}
 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Associativity {
+    Left,
+    Right,
+    /// `a < b < c` is an error
+    None,
+}
 
+/// binding power of the prefix operators `-`, `!` and `~`, only `**` binds
+/// tighter, so `-2 ** 2` is `-(2 ** 2)`
+pub const PREFIX_PRECEDENCE: u8 = 10;
 
-/// match `soft ->` or `soft =>`
-pub fn arrow(input: &str) -> Result<(&str, String), parsec::ParseError> {
-    combinators::soft(tokens(2, |x| x == "->" || x == "=>"))(input)
+/// binding power and associativity of a binary operator, from `||` the
+/// loosest to `**` the tightest
+pub fn binary_precedence(operator: &str) -> Option<(u8, Associativity)> {
+    let precedence = match operator {
+        "||" => 1,
+        "&&" => 2,
+        "==" | "!=" | "<" | "<=" | ">" | ">=" => return Some((3, Associativity::None)),
+        "|" => 4,
+        "^" => 5,
+        "&" => 6,
+        "<<" | ">>" => 7,
+        "+" | "-" => 8,
+        "*" | "/" | "%" => 9,
+        "**" => return Some((11, Associativity::Right)),
+        _ => return None,
+    };
+    Some((precedence, Associativity::Left))
 }
 
+/// minimum binding power of the operators in the right operand
+pub fn right_precedence(precedence: u8, associativity: Associativity) -> u8 {
+    match associativity {
+        Associativity::Right => precedence,
+        Associativity::Left | Associativity::None => precedence + 1,
+    }
+}
 
+/// what `a < b < c` lacks, comparisons do not associate
+pub const CHAINED: &str = "parentheses around chained comparisons";
 
-
-/// parameters must contain at least one parameter
-///
-/// e.g. `x` or `x y`
-pub fn parameters(input: &str) -> Result<(&str, Vec<Expr>), parsec::ParseError> {
-    parsec::map(parsec::some(combinators::soft(identifier)), |xs| {
-        xs.iter().map(|x| Expr::Identifier(x.to_string())).collect()
-    })(input)
+/// map a runtime grammar tree onto `Expr` by rule name
+/// - `Integer`, `Float`, `Number`, `Identifier`, `String` are literals
+/// - `Paren` wraps its only child
+/// - `Add` folds its children from left
+/// - `Function` takes its last child as body and the others as parameters
+/// - any other rule with exactly one child is transparent
+pub fn lower(tree: &Tree) -> Result<Expr, ParserError> {
+    let children = || {
+        tree.children
+            .iter()
+            .map(lower)
+            .collect::<Result<Vec<_>, _>>()
+    };
+    match tree.rule.as_str() {
+        "Integer" | "Float" | "Number" => match number(tree.text.trim()) {
+            Ok(("", x)) => Ok(number_expr(x)),
+            Ok(_) => err(&format!("`{}` is not a number", tree.text)),
+            Err(e) => err(&e.to_string()),
+        },
+        "Identifier" => Ok(Expr::Identifier(normalize(tree.text.trim()))),
+        // a grammar tree has no byte offsets, so it has no spans either
+        "String" => match quoted(tree.text.trim(), 0, Locator::new(0, 0)) {
+            Ok(mut x) => {
+                map_spans(&mut x, &|_| Span::default());
+                Ok(x)
+            }
+            _ => Ok(Expr::String(tree.text.clone())),
+        },
+        "Paren" => match children()?.pop() {
+            Some(expr) if tree.children.len() == 1 => Ok(Expr::paren(expr, Span::default())),
+            _ => err("`Paren` expects exactly one child"),
+        },
+        "Add" => children()?
+            .into_iter()
+            .reduce(|lhs, rhs| {
+                Expr::Add(Box::new(BinaryExpr {
+                    operator: "+".to_string(),
+                    lhs,
+                    rhs,
+                }))
+            })
+            .ok_or_else(|| ParserError::new("`Add` expects at least one child")),
+        "Function" => {
+            let mut params = children()?;
+            match params.pop() {
+                Some(body) => Ok(Expr::Function(Box::new(FunctionExpr {
+                    doc: None,
+                    params,
+                    body,
+                }))),
+                None => err("`Function` expects a body"),
+            }
+        }
+        _ if tree.children.len() == 1 => lower(&tree.children[0]),
+        rule => err(&format!("cannot lower rule {}", rule)),
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{ast::Item, parser::grammar::Grammar};
 
+    #[test]
+    fn lowers_grammar_trees_by_rule_name() {
+        let grammar = Grammar::load(
+            "Add <- Primary ('+' Primary)*
+             Primary <- Paren / Integer / Identifier
+             Paren <- '(' Add ')'
+             Integer <- [0-9]+
+             Identifier <- [a-z]+",
+        )
+        .unwrap();
+        let expr = lower(&grammar.parse("1+(x)").unwrap()).unwrap();
+        assert_eq!(
+            format!("{:?}", expr),
+            concat!(
+                r#"Add { operator: "+", lhs: Int { value: 1 }, rhs: Paren { expr: "x", span: "#,
+                r#"Span { start: 0, end: 0 } } }"#
+            )
+        );
+        let expr = lower(&grammar.parse("99999999999999999999").unwrap()).unwrap();
+        assert!(matches!(expr, Expr::BigInteger(_)));
+    }
 
-/// accelerator function
-/// - is expression
-/// - is anonymous function
-/// - is arrow function (ECMAScript Language Specification)
-///
-/// e.g. `x y -> x + y` or `x y => x + y`
-pub fn function(input: &str) -> Result<(&str, Expr), parsec::ParseError> {
-    let parser = parsec::follow(parsec::skip(parameters, arrow), expr);
-    let morph = |x: (Vec<Expr>, Expr)| {
-        Expr::Function(Box::new(FunctionExpr {
-            params: x.0,
-            body: x.1,
-        }))
-    };
-    parsec::map(parser, morph)(input)
-}
+    #[test]
+    fn operators_need_no_spaces() {
+        let x = parse("a+b").unwrap();
+        assert_eq!(format!("{:?}", x), r#"Add { operator: "+", lhs: "a", rhs: "b" }"#);
+        let Expr::Function(x) = parse("x /* y */\ty => x\u{3000}+ /* z */ y").unwrap() else {
+            panic!("a function is expected");
+        };
+        assert_eq!(format!("{:?}", x.params), r#"["x", "y"]"#);
+    }
+
+    #[test]
+    fn parses_through_the_syntax_tree() {
+        let Expr::Function(function) = parse("x y -> x + (1)").unwrap() else {
+            panic!("a function is expected");
+        };
+        assert_eq!(format!("{:?}", function.params), r#"["x", "y"]"#);
+        assert_eq!(
+            format!("{:?}", function.body),
+            concat!(
+                r#"Add { operator: "+", lhs: "x", rhs: Paren { expr: Int { value: 1 }, span: "#,
+                r#"Span { start: 11, end: 14 } } }"#
+            )
+        );
+        assert!(parse("x ->").is_err());
+        assert!(parse("").is_err());
+    }
+
+    #[test]
+    fn functions_keep_their_doc() {
+        let Ok(Expr::Function(x)) = parse("/// twice\n/// of x\nx -> x + x") else {
+            panic!("a function is expected");
+        };
+        assert_eq!(x.doc.as_deref(), Some("twice\nof x"));
+    }
+
+    #[test]
+    fn an_indented_block_groups_like_parentheses() {
+        let Expr::Function(function) = parse("x ->\n  x + 1").unwrap() else {
+            panic!("a function is expected");
+        };
+        assert_eq!(
+            format!("{:?}", function.body),
+            r#"Add { operator: "+", lhs: "x", rhs: Int { value: 1 } }"#
+        );
+        assert!(parse("x ->\n  x\n  1").is_err());
+    }
+
+    #[test]
+    fn returns_the_warnings_of_the_lexer() {
+        let (_, warnings) = parse_with_warnings("paypal + p\u{430}ypal").unwrap();
+        let messages: Vec<_> = warnings.iter().map(|x| x.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "identifier `p\u{430}ypal` mixes scripts",
+                "identifier `p\u{430}ypal` is confusable with `paypal`",
+            ]
+        );
+        let (_, warnings) = parse_with_warnings("paypal + 1").unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn parses_embedded_expressions() {
+        let x = parse("「こんにちは、${name}」").unwrap();
+        let expected = r#"Interpolation { parts: [Text("こんにちは、"), Expr("name")] }"#;
+        assert_eq!(format!("{:?}", x), expected);
+        let x = parse(r#""total: ${a + b}\$""#).unwrap();
+        let expected = concat!(
+            r#"Interpolation { parts: [Text("total: "), "#,
+            r#"Expr(Add { operator: "+", lhs: "a", rhs: "b" }), Text("$")] }"#
+        );
+        assert_eq!(format!("{:?}", x), expected);
+        let x = parse("'plain'").unwrap();
+        assert_eq!(format!("{:?}", x), r#"String { value: "plain" }"#);
+        let x = parse(r#""${1}""#).unwrap();
+        assert_eq!(format!("{:?}", x), r#"Interpolation { parts: [Expr(Int { value: 1 })] }"#);
+    }
+
+    #[test]
+    fn rejects_malformed_embedded_expressions() {
+        let e = parse("\"${a b)}\"").unwrap_err();
+        assert!(e.message.starts_with("unexpected `)`"), "{}", e.message);
+        assert!(parse("\"${}\"").is_err());
+    }
 
+    /// `x` with every operation in parentheses
+    fn grouped(x: &Expr) -> String {
+        match x {
+            Expr::Add(x)
+            | Expr::Sub(x)
+            | Expr::Mul(x)
+            | Expr::Div(x)
+            | Expr::Rem(x)
+            | Expr::Pow(x)
+            | Expr::Eq(x)
+            | Expr::Ne(x)
+            | Expr::Lt(x)
+            | Expr::Le(x)
+            | Expr::Gt(x)
+            | Expr::Ge(x)
+            | Expr::BitAnd(x)
+            | Expr::BitOr(x)
+            | Expr::BitXor(x)
+            | Expr::Shl(x)
+            | Expr::Shr(x)
+            | Expr::And(x)
+            | Expr::Or(x) => format!("({} {} {})", grouped(&x.lhs), x.operator, grouped(&x.rhs)),
+            Expr::Neg(x) | Expr::Not(x) | Expr::BitNot(x) => {
+                format!("({}{})", x.operator, grouped(&x.expr))
+            }
+            Expr::FunctionCall(x) => {
+                let args = x.args.iter().map(grouped).collect::<Vec<_>>();
+                format!("{}[{}]", grouped(&x.callee), args.join(", "))
+            }
+            Expr::Paren(x) => grouped(&x.expr),
+            Expr::Function(_) => format!("({})", x),
+            x => x.to_string(),
+        }
+    }
 
+    #[test]
+    fn operators_bind_by_precedence() {
+        let cases = [
+            ("a || b && c", "(a || (b && c))"),
+            ("a && b == c", "(a && (b == c))"),
+            ("a == b | c", "(a == (b | c))"),
+            ("a | b ^ c & d", "(a | (b ^ (c & d)))"),
+            ("a & b << c", "(a & (b << c))"),
+            ("a >> b + c", "(a >> (b + c))"),
+            ("a + b * c", "(a + (b * c))"),
+            ("a - b - c", "((a - b) - c)"),
+            ("a * b % c / d", "(((a * b) % c) / d)"),
+            ("2 ** 3 ** 2", "(2 ** (3 ** 2))"),
+            ("-2 ** 2", "(-(2 ** 2))"),
+            ("-a * b", "((-a) * b)"),
+            ("!a && ~b", "((!a) && (~b))"),
+            ("a <= b && c != d", "((a <= b) && (c != d))"),
+            ("a ＋ b ＊ c", "(a + (b * c))"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(grouped(&parse(source).unwrap()), expected, "{}", source);
+        }
+    }
 
+    #[test]
+    fn rejects_malformed_operations() {
+        let e = parse("a < b < c").unwrap_err();
+        assert!(e.message.starts_with("expected parentheses around chained"), "{}", e.message);
+        assert!(e.message.contains("column: 6"), "{}", e.message);
+        let e = parse("a == b < c").unwrap_err();
+        assert!(e.message.starts_with("expected parentheses around chained"), "{}", e.message);
+        assert!(parse("(a == b) < c").is_ok());
+        // `<+>` is no operator, the `+` in it is reported before the `<` and
+        // the `>` around it read as a chain
+        let e = parse("a <+> b").unwrap_err();
+        assert!(e.message.starts_with("unexpected `+`"), "{}", e.message);
+        let e = parse("a +").unwrap_err();
+        assert_eq!(e.message, "unexpected end of input: error at Locator { column: 3, row: 0 }.");
+        let e = parse("* a").unwrap_err();
+        assert!(e.message.starts_with("unexpected `*`"), "{}", e.message);
+    }
+
+    #[test]
+    fn application_binds_tighter_than_operators() {
+        let cases = [
+            ("f x y", "f[x][y]"),
+            ("f(x, y)", "f[x, y]"),
+            ("f()", "f[]"),
+            ("f x + g y", "(f[x] + g[y])"),
+            ("-f x ** 2", "(-(f[x] ** 2))"),
+            ("f 1 true 'a' (-x)", "f[1][true][\"a\"][(-x)]"),
+            ("x y => f x y", "(x y => f x y)"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(grouped(&parse(source).unwrap()), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_calls() {
+        let e = parse("f(a, b").unwrap_err();
+        assert!(e.message.starts_with("expected `)` to close the arguments"), "{}", e.message);
+        assert!(e.message.contains("column: 1"), "{}", e.message);
+        let e = parse("f(a,, b)").unwrap_err();
+        assert!(e.message.starts_with("unexpected `,`"), "{}", e.message);
+    }
+
+    #[test]
+    fn calls_any_expression() {
+        let cases = [
+            ("(x => x + 1) 2", "(x => x + 1)[2]"),
+            ("f a b", "f[a][b]"),
+            ("(f a) b", "f[a][b]"),
+            ("f (a b)", "f[a[b]]"),
+            ("f(a)(b)", "f[a][b]"),
+            ("\"s\" x", "\"s\"[x]"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(grouped(&parse(source).unwrap()), expected, "{}", source);
+        }
+        for source in ["(x => x + 1) 2", "f a b", "f (a b)", "f(a, b)", "(-1) (-a)"] {
+            assert_eq!(parse(source).unwrap().to_string(), source);
+        }
+    }
+
+    #[test]
+    fn commas_make_tuples() {
+        let Expr::Comma(x) = parse("a, b + 1, f c").unwrap() else {
+            panic!()
+        };
+        let items: Vec<_> = x.items.iter().map(grouped).collect();
+        assert_eq!(items, ["a", "(b + 1)", "f[c]"]);
+        let cases = [
+            ("(a, b), c", "(a, b), c"),
+            ("x => x, y", "x => x, y"),
+            ("(x, y) => x + y", "(x, y) => x + y"),
+            ("((a, b), c) d => a", "((a, b), c) d => a"),
+            ("f((a, b), c)", "f((a, b), c)"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse(source).unwrap().to_string(), expected, "{}", source);
+        }
+        // the body of a function does not take a `,`
+        let Expr::Comma(x) = parse("x => x, y").unwrap() else {
+            panic!()
+        };
+        assert!(matches!(x.items.as_slice(), [Expr::Function(_), Expr::Identifier(_)]));
+        let Expr::Function(x) = parse("(x, (y, z)) => x").unwrap() else {
+            panic!()
+        };
+        assert!(matches!(x.params.as_slice(), [Expr::Comma(_)]));
+    }
+
+    #[test]
+    fn rejects_malformed_tuples() {
+        let e = parse("a, ").unwrap_err();
+        assert!(e.message.starts_with("unexpected end of input"), "{}", e.message);
+        let e = parse("(a, b").unwrap_err();
+        assert!(e.message.starts_with("expected `)` to close `(`"), "{}", e.message);
+        let e = parse("(1, x) => x").unwrap_err();
+        assert!(e.message.starts_with("unexpected `=>`"), "{}", e.message);
+    }
+
+    #[test]
+    fn spans_are_offsets_in_the_source() {
+        let Expr::Paren(x) = parse("  (a)").unwrap() else { panic!() };
+        assert_eq!(x.span, Span { start: 2, end: 5 });
+        let Expr::Interpolation(x) = parse(r#""x ${(a)}""#).unwrap() else { panic!() };
+        let [TemplatePart::Text(_), TemplatePart::Expr(Expr::Paren(x))] = x.parts.as_slice() else {
+            panic!()
+        };
+        assert_eq!(x.span, Span { start: 5, end: 8 });
+    }
+
+    /// spans of the parentheses in `x`, outermost first
+    fn spans(x: &mut Expr) -> Vec<Span> {
+        let mut found = Vec::new();
+        if let Expr::Paren(x) = x {
+            found.push(x.span);
+        }
+        for x in x.children_mut() {
+            found.extend(spans(x));
+        }
+        found
+    }
+
+    #[test]
+    fn keeps_the_parentheses_with_their_spans() {
+        let source = [
+            "let a = ((x))",
+            "",
+            "  // c",
+            "f (y + 1) \"${ (z) } ${g((w))}\"",
+            "def h x =",
+            "  let y = (x)",
+            "  y",
+        ]
+        .join("\n");
+        let program = parse_program(&source).unwrap();
+        let mut found = Vec::new();
+        for item in program.items {
+            let mut x = match item {
+                Item::Let(x) => x.value,
+                Item::Expr(x) => x,
+            };
+            found.extend(spans(&mut x));
+        }
+        let texts: Vec<_> = found.iter().map(|x| &source[x.start..x.end]).collect();
+        assert_eq!(texts, ["((x))", "(x)", "(y + 1)", "(z)", "(w)", "(x)"]);
+    }
+
+    #[test]
+    fn grammar_trees_have_no_spans() {
+        let grammar = Grammar::load("Paren <- '(' Identifier ')'\nIdentifier <- [a-z]+").unwrap();
+        let Expr::Paren(x) = lower(&grammar.parse("(x)").unwrap()).unwrap() else {
+            panic!()
+        };
+        assert_eq!(x.span, Span::default());
+    }
+
+    #[test]
+    fn parses_programs() {
+        let x = parse_program("def square x = x * x\nlet y = square 3; y + 1").unwrap();
+        assert_eq!(x.to_string(), "let rec square = x => x * x\nlet y = square 3\ny + 1\n");
+        assert!(parse("let y = 1").is_err());
+        assert!(parse("a; b").is_err());
+    }
+
+    /// items of `source` printed one per line
+    fn items(source: &str) -> String {
+        match parse_program(source) {
+            Ok(x) => x.to_string(),
+            Err(e) => e.message,
+        }
+    }
+
+    #[test]
+    fn parses_definitions() {
+        let source = "/// the answer\nlet x = 42\n\
+         let rec f = n => f n\ndef g = 1\ndef h (a, b) c = a";
+        let expected = [
+            "/// the answer",
+            "let x = 42",
+            "let rec f = n => f n",
+            "let rec g = 1",
+            "let rec h = (a, b) c => a",
+            "",
+        ];
+        assert_eq!(items(source), expected.join("\n"));
+        assert_eq!(items(""), "");
+        assert!(items("let x").starts_with("expected `=` in the definition"));
+        assert!(items("let 1 = 2").starts_with("expected a name to define"));
+        assert!(items("let x = 1 let y = 2").starts_with("unexpected `let`"));
+    }
+
+    #[test]
+    fn a_line_break_ends_an_item() {
+        assert_eq!(items("let x = 1\n-2"), "let x = 1\n-2\n");
+        assert_eq!(items("f\nx"), "f\nx\n");
+        assert_eq!(items("a;\nb; c"), "a\nb\nc\n");
+        assert!(items("1 +\n2").starts_with("unexpected line break"));
+    }
+
+    #[test]
+    fn an_indented_line_goes_on_with_an_operator() {
+        assert_eq!(items("let x = 1\n  - 2"), "let x = 1 - 2\n");
+        assert_eq!(items("let x = a * b\n  + c\n  + d\nx"), "let x = a * b + c + d\nx\n");
+        assert_eq!(items("let x = (1\n-2)\nf(a,\nb)"), "let x = (1 - 2)\nf(a, b)\n");
+        let e = items("a\n  b");
+        assert!(e.starts_with("unexpected indentation"), "{}", e);
+        assert!(e.contains("column: 2, row: 1"), "{}", e);
+    }
+
+    #[test]
+    fn keywords_go_on_across_lines() {
+        assert_eq!(items("if c then a\nelse b\nd"), "if c then a else b\nd\n");
+        let x = items("match x with\n  | 0 => 1\n  | _ => 2\ny");
+        assert_eq!(x, "match x with | 0 => 1 | _ => 2\ny\n");
+        let x = items("match x with | 0 => 1\n| _ => 2");
+        assert_eq!(x, "match x with | 0 => 1 | _ => 2\n");
+    }
+
+    #[test]
+    fn an_indented_block_is_definitions_and_a_result() {
+        let x = items("def f x =\n  let y = x\n  y + 1\nf 2");
+        assert_eq!(x, "let rec f = x => (let y = x; y + 1)\nf 2\n");
+        assert_eq!(items(&x), x);
+        assert_eq!(items("let x =\n  1\n    + 2\nx"), "let x = 1 + 2\nx\n");
+        assert_eq!(items("let f = x =>\n  x\nf"), "let f = x => x\nf\n");
+        let x = items("if c then\n  let y = 1; y\nelse\n  2");
+        assert_eq!(x, "if c then (let y = 1; y) else 2\n");
+    }
+
+    #[test]
+    fn rejects_malformed_blocks() {
+        let e = items("let x =\n  a\n  b");
+        assert!(e.starts_with("expected a definition"), "{}", e);
+        assert!(e.contains("column: 2, row: 1"), "{}", e);
+        let e = items("let x =\n  let y = 1\nx");
+        assert!(e.starts_with("expected the result of the block"), "{}", e);
+        let e = items("let x =\n  1 )\nx");
+        assert!(e.starts_with("unexpected `)`"), "{}", e);
+        let e = items("(let y = 1; y");
+        assert!(e.starts_with("expected `)`"), "{}", e);
+    }
+
+    #[test]
+    fn parses_conditionals() {
+        let x = parse("if a then b else c + 1").unwrap();
+        assert_eq!(x.to_string(), "if a then b else c + 1");
+        let Expr::If(x) = x else { panic!() };
+        assert_eq!(grouped(&x.alternative), "(c + 1)");
+        let x = parse("if a then if b then c else d else e").unwrap();
+        assert_eq!(x.to_string(), "if a then if b then c else d else e");
+        let x = parse("(if a then b else c) + 1").unwrap();
+        assert_eq!(x.to_string(), "(if a then b else c) + 1");
+        let x = items("def f x =\n  if x > 0\n  then 1\n  else 0");
+        assert_eq!(x, "let rec f = x => if x > 0 then 1 else 0\n");
+    }
+
+    #[test]
+    fn rejects_malformed_conditionals() {
+        let e = parse("if a then b").unwrap_err();
+        assert!(e.message.starts_with("expected `then` and `else`"), "{}", e.message);
+        let e = parse("if a else b").unwrap_err();
+        assert!(e.message.starts_with("expected `then` and `else`"), "{}", e.message);
+        assert!(parse("if then a else b").is_err());
+    }
+
+    #[test]
+    fn parses_matches() {
+        let source = "match x with 0 => a | (y, _) => b | y if y > 1 => c | _ => d";
+        let x = parse(source).unwrap();
+        let expected = "match x with | 0 => a | y, _ => b | y if y > 1 => c | _ => d";
+        assert_eq!(x.to_string(), expected);
+        assert_eq!(parse(expected).unwrap().to_string(), expected);
+        let Expr::Match(x) = x else { panic!() };
+        let patterns: Vec<_> = x.arms.iter().map(|x| x.pattern.to_string()).collect();
+        assert_eq!(patterns, ["0", "y, _", "y", "_"]);
+        let guards: Vec<_> = x.arms.iter().map(|x| x.guard.is_some()).collect();
+        assert_eq!(guards, [false, false, true, false]);
+        let x = parse("match b with true => 1 | false => 2").unwrap();
+        assert_eq!(x.to_string(), "match b with | true => 1 | false => 2");
+        let x = parse("match x with\n  | 1 => a\n  | _ => b").unwrap();
+        assert_eq!(x.to_string(), "match x with | 1 => a | _ => b");
+        assert!(parse("match x with\n| 1 => a").is_err());
+    }
+
+    #[test]
+    fn arm_bodies_end_before_a_bar() {
+        let x = parse("match x with 1 => a + b | _ => (c || d)").unwrap();
+        assert_eq!(x.to_string(), "match x with | 1 => a + b | _ => (c || d)");
+        let Expr::Match(x) = x else { panic!() };
+        assert_eq!(grouped(&x.arms[0].body), "(a + b)");
+        let source = "match x with 1 => a == b | 2 => a && b || c | _ => if c then d else e";
+        let x = parse(source).unwrap();
+        let expected = "match x with | 1 => a == b | 2 => a && b || c | _ => if c then d else e";
+        assert_eq!(x.to_string(), expected);
+        let Expr::Match(x) = x else { panic!() };
+        let bodies: Vec<_> = x.arms.iter().map(|x| grouped(&x.body)).collect();
+        assert_eq!(bodies, ["(a == b)", "((a && b) || c)", "if c then d else e"]);
+        // a `|` which no pattern and arrow follow is the bitwise operator
+        let x = parse("match x with 1 => a | b | y => y | b").unwrap();
+        assert_eq!(x.to_string(), "match x with | 1 => a | b | y => y | b");
+        let Expr::Match(x) = x else { panic!() };
+        assert_eq!(x.arms.len(), 2);
+        let x = parse("match x with 1 => y => y, 2 | _ => match y with 0 => a | _ => b").unwrap();
+        let expected = "match x with | 1 => (y => y, 2) | _ => match y with | 0 => a | _ => b";
+        assert_eq!(x.to_string(), expected);
+        assert_eq!(parse(expected).unwrap().to_string(), expected);
+        // in brackets a `|` ends no arm
+        let x = parse("match x with 1 => (a | b, c => c) | _ => d").unwrap();
+        assert_eq!(x.to_string(), "match x with | 1 => (a | b, c => c) | _ => d");
+        let x = parse("(match x with _ => a) + 1").unwrap();
+        assert_eq!(x.to_string(), "(match x with | _ => a) + 1");
+    }
+
+    #[test]
+    fn parses_negative_literal_patterns() {
+        let x = parse("match x with -1 => a | - 2.5 => b | (-3, 0) => c | _ => d").unwrap();
+        assert_eq!(x.to_string(), "match x with | -1 => a | -2.5 => b | -3, 0 => c | _ => d");
+        let Expr::Match(x) = x else { panic!() };
+        let patterns: Vec<_> = x.arms.iter().map(|x| format!("{:?}", x.pattern)).collect();
+        assert_eq!(patterns[0], "Int { value: -1 }");
+        assert_eq!(patterns[1], "Float { value: -2.5 }");
+        let e = parse("match x with -y => 1 | _ => 2").unwrap_err();
+        assert!(e.message.starts_with("expected `=>` in the match arm"), "{}", e.message);
+    }
+
+    #[test]
+    fn rejects_matches_which_are_not_exhaustive() {
+        let e = parse("match true with true => 1").unwrap_err();
+        assert!(e.message.starts_with("expected an arm of `_`"), "{}", e.message);
+        assert!(e.message.contains("column: 0, row: 0"), "{}", e.message);
+        assert!(parse("match 3 with 1 => 1 | -1 => 2").is_err());
+        assert!(parse("match x with (true, _) => 1 | (_, false) => 2").is_err());
+        assert!(parse("match x with n if n > 0 => 1").is_err());
+        assert!(parse("match x with (false, _) => 1 | (true, n) => n").is_ok());
+        let e = items("let f = x =>\n  match x with\n    | 0 => 1\n    | 1 => 2\nf");
+        assert!(e.starts_with("expected an arm of `_`"), "{}", e);
+        assert!(e.contains("column: 2, row: 1"), "{}", e);
+    }
+
+    #[test]
+    fn rejects_malformed_matches() {
+        let e = parse("match x").unwrap_err();
+        assert!(e.message.starts_with("expected `with` and arms"), "{}", e.message);
+        let e = parse("match x with 1 a").unwrap_err();
+        assert!(e.message.starts_with("expected `=>` in the match arm"), "{}", e.message);
+        assert!(e.message.contains("column: 13"), "{}", e.message);
+        let e = parse("match x with 1 => a |").unwrap_err();
+        assert!(e.message.starts_with("unexpected end of input"), "{}", e.message);
+        assert!(e.message.contains("column: 21"), "{}", e.message);
+        let e = parse("match x with a + b => 1").unwrap_err();
+        assert!(e.message.starts_with("expected `=>` in the match arm"), "{}", e.message);
+    }
+}
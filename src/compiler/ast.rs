@@ -1,14 +1,29 @@
+use super::{
+    accelerator::{binary_precedence, Associativity, PREFIX_PRECEDENCE},
+    bigint::BigInt,
+};
+
 #[derive(Clone, Hash)]
 // #[non_exhaustive]
 pub enum Expr {
     Integer(i64),
 
-    // Float(f64),
+    /// integer literal which does not fit in an `i64`
+    BigInteger(BigInt),
+
+    Float(Float),
 
     Identifier(String),
 
     String(String),
 
+    /// `true` or `false`
+    Boolean(bool),
+
+    /// string with embedded expressions, e.g. `"total: ${a + b}"`
+    Interpolation(Box<InterpolationExpr>),
+
+    /// tuple, e.g. `a, b, c` or `(x, y)`
     Comma(Box<CommaExpr>),
 
     Paren(Box<ParenExpr>),
@@ -17,12 +32,389 @@ pub enum Expr {
 
     FunctionCall(Box<FunctionCallExpr>),
 
+    /// `if condition then consequent else alternative`
+    If(Box<IfExpr>),
+
+    /// `match scrutinee with | pattern => body | pattern if guard => body`
+    Match(Box<MatchExpr>),
+
+    /// `let` items and a result in a scope of their own, an indented block
+    /// or `(let y = x; y + 1)`
+    Block(Box<BlockExpr>),
+
     Add(Box<BinaryExpr>),
+    /// lhs `-` rhs
+    Sub(Box<BinaryExpr>),
+    /// lhs `*` rhs
+    Mul(Box<BinaryExpr>),
+    /// lhs `/` rhs
+    Div(Box<BinaryExpr>),
+    /// lhs `%` rhs
+    Rem(Box<BinaryExpr>),
+    /// lhs `**` rhs
+    Pow(Box<BinaryExpr>),
+
+    /// lhs `==` rhs
+    Eq(Box<BinaryExpr>),
+    /// lhs `!=` rhs
+    Ne(Box<BinaryExpr>),
+    /// lhs `<` rhs
+    Lt(Box<BinaryExpr>),
+    /// lhs `<=` rhs
+    Le(Box<BinaryExpr>),
+    /// lhs `>` rhs
+    Gt(Box<BinaryExpr>),
+    /// lhs `>=` rhs
+    Ge(Box<BinaryExpr>),
+
+    /// lhs `&` rhs
+    BitAnd(Box<BinaryExpr>),
+    /// lhs `|` rhs
+    BitOr(Box<BinaryExpr>),
+    /// lhs `^` rhs
+    BitXor(Box<BinaryExpr>),
+    /// lhs `<<` rhs
+    Shl(Box<BinaryExpr>),
+    /// lhs `>>` rhs
+    Shr(Box<BinaryExpr>),
 
     /// lhs `&&` rhs
     And(Box<BinaryExpr>),
     /// lhs `||` rhs
     Or(Box<BinaryExpr>),
+
+    /// `-` expr
+    Neg(Box<UnaryExpr>),
+    /// `!` expr
+    Not(Box<UnaryExpr>),
+    /// `~` expr
+    BitNot(Box<UnaryExpr>),
+}
+
+impl Expr {
+    /// `lhs operator rhs` if `operator` is a binary one
+    pub fn binary(operator: &str, lhs: Expr, rhs: Expr) -> Option<Expr> {
+        let variant = match operator {
+            "+" => Expr::Add,
+            "-" => Expr::Sub,
+            "*" => Expr::Mul,
+            "/" => Expr::Div,
+            "%" => Expr::Rem,
+            "**" => Expr::Pow,
+            "==" => Expr::Eq,
+            "!=" => Expr::Ne,
+            "<" => Expr::Lt,
+            "<=" => Expr::Le,
+            ">" => Expr::Gt,
+            ">=" => Expr::Ge,
+            "&" => Expr::BitAnd,
+            "|" => Expr::BitOr,
+            "^" => Expr::BitXor,
+            "<<" => Expr::Shl,
+            ">>" => Expr::Shr,
+            "&&" => Expr::And,
+            "||" => Expr::Or,
+            _ => return None,
+        };
+        let operator = operator.to_string();
+        Some(variant(Box::new(BinaryExpr { operator, lhs, rhs })))
+    }
+
+    /// `operator expr` if `operator` is a prefix one
+    pub fn unary(operator: &str, expr: Expr) -> Option<Expr> {
+        let variant = match operator {
+            "-" => Expr::Neg,
+            "!" => Expr::Not,
+            "~" => Expr::BitNot,
+            _ => return None,
+        };
+        let operator = operator.to_string();
+        Some(variant(Box::new(UnaryExpr { operator, expr })))
+    }
+
+    /// `callee args`
+    pub fn call(callee: Expr, args: Vec<Expr>) -> Expr {
+        Expr::FunctionCall(Box::new(FunctionCallExpr { callee, args }))
+    }
+
+    /// `(expr)` written at `span`
+    pub fn paren(expr: Expr, span: Span) -> Expr {
+        Expr::Paren(Box::new(ParenExpr { expr, span }))
+    }
+
+    /// direct subexpressions, e.g. for a pass over the whole tree
+    pub fn children_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Expr::Integer(_)
+            | Expr::BigInteger(_)
+            | Expr::Float(_)
+            | Expr::Identifier(_)
+            | Expr::String(_)
+            | Expr::Boolean(_) => Vec::new(),
+            Expr::Interpolation(x) => x
+                .parts
+                .iter_mut()
+                .filter_map(|x| match x {
+                    TemplatePart::Expr(x) => Some(x),
+                    TemplatePart::Text(_) => None,
+                })
+                .collect(),
+            Expr::Comma(x) => x.items.iter_mut().collect(),
+            Expr::Paren(x) => vec![&mut x.expr],
+            Expr::Function(x) => x.params.iter_mut().chain([&mut x.body]).collect(),
+            Expr::FunctionCall(x) => [&mut x.callee].into_iter().chain(&mut x.args).collect(),
+            Expr::If(x) => vec![&mut x.condition, &mut x.consequent, &mut x.alternative],
+            Expr::Match(x) => {
+                let mut children = vec![&mut x.scrutinee];
+                for arm in &mut x.arms {
+                    children.push(&mut arm.pattern);
+                    children.extend(&mut arm.guard);
+                    children.push(&mut arm.body);
+                }
+                children
+            }
+            Expr::Block(x) => (x.items.iter_mut().map(|x| &mut x.value))
+                .chain([&mut x.result])
+                .collect(),
+            Expr::Neg(x) | Expr::Not(x) | Expr::BitNot(x) => vec![&mut x.expr],
+            Expr::Add(x)
+            | Expr::Sub(x)
+            | Expr::Mul(x)
+            | Expr::Div(x)
+            | Expr::Rem(x)
+            | Expr::Pow(x)
+            | Expr::Eq(x)
+            | Expr::Ne(x)
+            | Expr::Lt(x)
+            | Expr::Le(x)
+            | Expr::Gt(x)
+            | Expr::Ge(x)
+            | Expr::BitAnd(x)
+            | Expr::BitOr(x)
+            | Expr::BitXor(x)
+            | Expr::Shl(x)
+            | Expr::Shr(x)
+            | Expr::And(x)
+            | Expr::Or(x) => vec![&mut x.lhs, &mut x.rhs],
+        }
+    }
+
+    /// `items` as a tuple, or the only item itself
+    pub fn tuple(mut items: Vec<Expr>) -> Expr {
+        match items.len() {
+            1 => items.remove(0),
+            _ => Expr::Comma(Box::new(CommaExpr { items })),
+        }
+    }
+
+    /// binding power of the expression when printed, see
+    /// `accelerator::binary_precedence`
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Comma(..) | Expr::Function(..) | Expr::If(..) | Expr::Match(..) => 0,
+            Expr::Integer(x) if *x < 0 => PREFIX_PRECEDENCE,
+            Expr::Float(x) if x.0.is_sign_negative() => PREFIX_PRECEDENCE,
+            Expr::BigInteger(x) if x.is_negative() => PREFIX_PRECEDENCE,
+            Expr::Neg(..) | Expr::Not(..) | Expr::BitNot(..) => PREFIX_PRECEDENCE,
+            Expr::FunctionCall(..) => CALL_PRECEDENCE,
+            Expr::Add(x)
+            | Expr::Sub(x)
+            | Expr::Mul(x)
+            | Expr::Div(x)
+            | Expr::Rem(x)
+            | Expr::Pow(x)
+            | Expr::Eq(x)
+            | Expr::Ne(x)
+            | Expr::Lt(x)
+            | Expr::Le(x)
+            | Expr::Gt(x)
+            | Expr::Ge(x)
+            | Expr::BitAnd(x)
+            | Expr::BitOr(x)
+            | Expr::BitXor(x)
+            | Expr::Shl(x)
+            | Expr::Shr(x)
+            | Expr::And(x)
+            | Expr::Or(x) => binary_precedence(&x.operator).map_or(0, |x| x.0),
+            _ => ATOM_PRECEDENCE,
+        }
+    }
+
+    /// print as an item of a tuple or of an argument list, or as a parameter
+    /// or the body of a function, where only a tuple needs parentheses
+    fn fmt_item(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Comma(_) => write!(f, "({})", self),
+            _ => write!(f, "{}", self),
+        }
+    }
+
+    /// print in parentheses if the expression binds looser than `min`
+    fn fmt_with(&self, f: &mut std::fmt::Formatter<'_>, min: u8) -> std::fmt::Result {
+        match self.precedence() < min {
+            true => write!(f, "({})", self),
+            false => write!(f, "{}", self),
+        }
+    }
+}
+
+/// binding power of application, tighter than any operator
+const CALL_PRECEDENCE: u8 = 12;
+/// binding power of literals, names and parentheses
+const ATOM_PRECEDENCE: u8 = 13;
+
+/// quoted form of a string literal, escaped so that it reads back the same
+fn fmt_text(f: &mut std::fmt::Formatter<'_>, text: &str) -> std::fmt::Result {
+    for x in text.chars() {
+        match x {
+            '\\' => f.write_str("\\\\")?,
+            '"' => f.write_str("\\\"")?,
+            '$' => f.write_str("\\$")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            '\r' => f.write_str("\\r")?,
+            '\0' => f.write_str("\\0")?,
+            x => write!(f, "{}", x)?,
+        }
+    }
+    Ok(())
+}
+
+/// source form of the expression, with the parentheses which the grouping
+/// needs
+///
+/// e.g. `(x => x + 1) 2` or `f(a, b) * -c`
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Integer(x) => write!(f, "{}", x),
+            Expr::BigInteger(x) => write!(f, "{}", x),
+            Expr::Float(x) => write!(f, "{:?}", x.0),
+            Expr::Identifier(x) => f.write_str(x),
+            Expr::Boolean(x) => write!(f, "{}", x),
+            Expr::String(x) => {
+                f.write_str("\"")?;
+                fmt_text(f, x)?;
+                f.write_str("\"")
+            }
+            Expr::Interpolation(x) => {
+                f.write_str("\"")?;
+                for part in &x.parts {
+                    match part {
+                        TemplatePart::Text(x) => fmt_text(f, x)?,
+                        TemplatePart::Expr(x) => write!(f, "${{{}}}", x)?,
+                    }
+                }
+                f.write_str("\"")
+            }
+            Expr::Comma(x) => {
+                for (i, item) in x.items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    item.fmt_item(f)?;
+                }
+                Ok(())
+            }
+            Expr::Paren(x) => write!(f, "({})", x.expr),
+            Expr::Function(x) => {
+                for param in &x.params {
+                    param.fmt_item(f)?;
+                    f.write_str(" ")?;
+                }
+                f.write_str("=> ")?;
+                x.body.fmt_item(f)
+            }
+            Expr::FunctionCall(x) => {
+                x.callee.fmt_with(f, CALL_PRECEDENCE)?;
+                match x.args.as_slice() {
+                    [arg] => {
+                        f.write_str(" ")?;
+                        arg.fmt_with(f, ATOM_PRECEDENCE)
+                    }
+                    args => {
+                        f.write_str("(")?;
+                        for (i, arg) in args.iter().enumerate() {
+                            if i > 0 {
+                                f.write_str(", ")?;
+                            }
+                            arg.fmt_item(f)?;
+                        }
+                        f.write_str(")")
+                    }
+                }
+            }
+            Expr::If(x) => {
+                f.write_str("if ")?;
+                x.condition.fmt_item(f)?;
+                f.write_str(" then ")?;
+                x.consequent.fmt_item(f)?;
+                f.write_str(" else ")?;
+                x.alternative.fmt_item(f)
+            }
+            Expr::Match(x) => {
+                f.write_str("match ")?;
+                x.scrutinee.fmt_item(f)?;
+                f.write_str(" with")?;
+                for (i, arm) in x.arms.iter().enumerate() {
+                    write!(f, " | {}", arm.pattern)?;
+                    if let Some(guard) = &arm.guard {
+                        f.write_str(" if ")?;
+                        guard.fmt_with(f, 1)?;
+                    }
+                    f.write_str(" => ")?;
+                    // a match at the end of the body would take the arms
+                    // after it
+                    match i + 1 < x.arms.len() {
+                        true => arm.body.fmt_with(f, 1)?,
+                        false => arm.body.fmt_item(f)?,
+                    }
+                }
+                Ok(())
+            }
+            Expr::Block(x) => {
+                f.write_str("(")?;
+                for item in &x.items {
+                    write!(f, "{}; ", item)?;
+                }
+                write!(f, "{})", x.result)
+            }
+            Expr::Neg(x) | Expr::Not(x) | Expr::BitNot(x) => {
+                f.write_str(&x.operator)?;
+                x.expr.fmt_with(f, PREFIX_PRECEDENCE)
+            }
+            Expr::Add(x)
+            | Expr::Sub(x)
+            | Expr::Mul(x)
+            | Expr::Div(x)
+            | Expr::Rem(x)
+            | Expr::Pow(x)
+            | Expr::Eq(x)
+            | Expr::Ne(x)
+            | Expr::Lt(x)
+            | Expr::Le(x)
+            | Expr::Gt(x)
+            | Expr::Ge(x)
+            | Expr::BitAnd(x)
+            | Expr::BitOr(x)
+            | Expr::BitXor(x)
+            | Expr::Shl(x)
+            | Expr::Shr(x)
+            | Expr::And(x)
+            | Expr::Or(x) => {
+                let (precedence, associativity) =
+                    binary_precedence(&x.operator).unwrap_or((0, Associativity::None));
+                let (lhs, rhs) = match associativity {
+                    Associativity::Left => (precedence, precedence + 1),
+                    Associativity::Right => (precedence + 1, precedence),
+                    Associativity::None => (precedence + 1, precedence + 1),
+                };
+                x.lhs.fmt_with(f, lhs)?;
+                write!(f, " {} ", x.operator)?;
+                x.rhs.fmt_with(f, rhs)
+            }
+        }
+    }
 }
 
 impl std::fmt::Debug for Expr {
@@ -32,11 +424,44 @@ impl std::fmt::Debug for Expr {
         match self {
             Expr::FunctionCall(x, ..) => std::fmt::Debug::fmt(x, f),
 
-            Expr::And(x) | Expr::Or(x) => {
+            Expr::Sub(x)
+            | Expr::Mul(x)
+            | Expr::Div(x)
+            | Expr::Rem(x)
+            | Expr::Pow(x)
+            | Expr::Eq(x)
+            | Expr::Ne(x)
+            | Expr::Lt(x)
+            | Expr::Le(x)
+            | Expr::Gt(x)
+            | Expr::Ge(x)
+            | Expr::BitAnd(x)
+            | Expr::BitOr(x)
+            | Expr::BitXor(x)
+            | Expr::Shl(x)
+            | Expr::Shr(x)
+            | Expr::And(x)
+            | Expr::Or(x) => {
                 let op_name = match self {
+                    Self::Sub(..) => "Sub",
+                    Self::Mul(..) => "Mul",
+                    Self::Div(..) => "Div",
+                    Self::Rem(..) => "Rem",
+                    Self::Pow(..) => "Pow",
+                    Self::Eq(..) => "Eq",
+                    Self::Ne(..) => "Ne",
+                    Self::Lt(..) => "Lt",
+                    Self::Le(..) => "Le",
+                    Self::Gt(..) => "Gt",
+                    Self::Ge(..) => "Ge",
+                    Self::BitAnd(..) => "BitAnd",
+                    Self::BitOr(..) => "BitOr",
+                    Self::BitXor(..) => "BitXor",
+                    Self::Shl(..) => "Shl",
+                    Self::Shr(..) => "Shr",
                     Self::And(..) => "And",
                     Self::Or(..) => "Or",
-                    expr => unreachable!("`And`, `Or` or `Coalesce` expected but gets {:?}", expr),
+                    expr => unreachable!("binary operator expected but gets {:?}", expr),
                 };
 
                 f.debug_struct(op_name)
@@ -44,69 +469,393 @@ impl std::fmt::Debug for Expr {
                     .field("rhs", &x.rhs)
                     .finish()
             }
+            Expr::Neg(x) | Expr::Not(x) | Expr::BitNot(x) => {
+                let op_name = match self {
+                    Self::Neg(..) => "Neg",
+                    Self::Not(..) => "Not",
+                    Self::BitNot(..) => "BitNot",
+                    expr => unreachable!("prefix operator expected but gets {:?}", expr),
+                };
+
+                f.debug_struct(op_name).field("expr", &x.expr).finish()
+            }
             Expr::Identifier(x) => std::fmt::Debug::fmt(x, f),
-            Expr::Function(x) => f
-                .debug_struct("Function")
-                .field("params", &x.params)
-                .field("body", &x.body)
-                .finish(),
+            Expr::Function(x) => {
+                let mut ds = f.debug_struct("Function");
+                if let Some(doc) = &x.doc {
+                    ds.field("doc", doc);
+                }
+                ds.field("params", &x.params).field("body", &x.body);
+                ds.finish()
+            }
             Expr::Add(x) => f
                 .debug_struct("Add")
                 .field("operator", &x.operator)
                 .field("lhs", &x.lhs)
                 .field("rhs", &x.rhs)
                 .finish(),
-            Expr::Comma(x) => f
-                .debug_struct("Comma")
-                .field("lhs", &x.lhs)
-                .field("rhs", &x.rhs)
+            Expr::Comma(x) => f.debug_struct("Comma").field("items", &x.items).finish(),
+            Expr::Paren(x) => f
+                .debug_struct("Paren")
+                .field("expr", &x.expr)
+                .field("span", &x.span)
                 .finish(),
-            Expr::Paren(x) => f.debug_struct("Paren").field("expr", &x.expr).finish(),
             Expr::Integer(x) => f.debug_struct("Int").field("value", x).finish(),
+            Expr::BigInteger(x) => f.debug_struct("BigInt").field("value", x).finish(),
+            Expr::Float(x) => f.debug_struct("Float").field("value", &x.0).finish(),
             Expr::String(x) => f.debug_struct("String").field("value", x).finish(),
+            Expr::Boolean(x) => f.debug_struct("Boolean").field("value", x).finish(),
+            Expr::If(x) => f
+                .debug_struct("If")
+                .field("condition", &x.condition)
+                .field("consequent", &x.consequent)
+                .field("alternative", &x.alternative)
+                .finish(),
+            Expr::Match(x) => f
+                .debug_struct("Match")
+                .field("scrutinee", &x.scrutinee)
+                .field("arms", &x.arms)
+                .finish(),
+            Expr::Interpolation(x) => f
+                .debug_struct("Interpolation")
+                .field("parts", &x.parts)
+                .finish(),
+            Expr::Block(x) => f
+                .debug_struct("Block")
+                .field("items", &x.items)
+                .field("result", &x.result)
+                .finish(),
         }?;
         write!(f, "")
     }
 }
 
+/// `f64` hashed by its bits, so that `Expr` can be hashed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Float(pub f64);
+
+impl std::hash::Hash for Float {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state)
+    }
+}
+
+#[derive(Clone, Debug, Hash)]
+pub enum TemplatePart {
+    Text(String),
+    Expr(Expr),
+}
+
+#[derive(Clone, Hash)]
+pub struct InterpolationExpr {
+    pub parts: Vec<TemplatePart>,
+}
+
 #[derive(Clone, Hash)]
 pub struct ParenExpr {
     pub expr: Expr,
+    /// the parentheses in the source, so that tools see the grouping of the
+    /// author
+    pub span: Span,
+}
+
+/// byte range in the source
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
 }
 
 #[derive(Clone, Hash)]
 pub struct CommaExpr {
-    pub lhs: Expr,
-    pub rhs: Expr,
+    /// at least two
+    pub items: Vec<Expr>,
 }
 
 #[derive(Clone, Hash)]
 pub struct FunctionExpr {
+    /// text of the `///` comments before the function
+    pub doc: Option<String>,
     pub params: Vec<Expr>,
     pub body: Expr,
 }
 
 #[derive(Clone, Hash)]
 pub struct FunctionCallExpr {
-    /// Function name.
-    pub name: String,
+    /// Called expression, e.g. a name, a call or an arrow function.
+    pub callee: Expr,
     /// List of function call argument expressions.
     pub args: Vec<Expr>,
 }
 
+#[derive(Clone, Hash)]
+pub struct IfExpr {
+    /// a boolean
+    pub condition: Expr,
+    pub consequent: Expr,
+    pub alternative: Expr,
+}
+
+#[derive(Clone, Hash)]
+pub struct MatchExpr {
+    pub scrutinee: Expr,
+    /// tried in order, the first one which matches is taken
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Clone, Hash)]
+pub struct BlockExpr {
+    /// run in order, each one in the scope of the ones before it
+    pub items: Vec<LetItem>,
+    pub result: Expr,
+}
+
+#[derive(Clone, Debug, Hash)]
+pub struct MatchArm {
+    /// a literal, a name, `_` or a tuple of patterns
+    pub pattern: Expr,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+impl MatchExpr {
+    /// whether the arms without a guard cover every value, as far as their
+    /// patterns tell the type of the scrutinee, see `covers`
+    pub fn is_exhaustive(&self) -> bool {
+        let rows: Vec<_> = self
+            .arms
+            .iter()
+            .filter(|x| x.guard.is_none())
+            .map(|x| vec![&x.pattern])
+            .collect();
+        covers(&rows, 1)
+    }
+}
+
+/// whether the rows of patterns cover every value of their `width` columns,
+/// the usefulness check of ML compilers
+///
+/// the type of a column is told by its patterns, a boolean if one of them is
+/// `true` or `false`, a tuple if they are names and tuples of one length,
+/// or else a type which only a name or `_` covers
+fn covers(rows: &[Vec<&Expr>], width: usize) -> bool {
+    if width == 0 {
+        return !rows.is_empty();
+    }
+    let heads: Vec<_> = rows.iter().map(|x| unparen(x[0])).collect();
+    let boolean = heads
+        .iter()
+        .all(|x| matches!(x, Expr::Identifier(_) | Expr::Boolean(_)));
+    let arity = heads.iter().find_map(|x| match x {
+        Expr::Comma(x) => Some(x.items.len()),
+        _ => None,
+    });
+    let arity = arity.filter(|&n| {
+        heads.iter().all(|x| match x {
+            Expr::Identifier(_) => true,
+            Expr::Comma(x) => x.items.len() == n,
+            _ => false,
+        })
+    });
+    if boolean && heads.iter().any(|x| matches!(x, Expr::Boolean(_))) {
+        return [true, false].into_iter().all(|value| {
+            let rows: Vec<_> = rows
+                .iter()
+                .zip(&heads)
+                .filter(|(_, x)| !matches!(x, Expr::Boolean(x) if *x != value))
+                .map(|(row, _)| row[1..].to_vec())
+                .collect();
+            covers(&rows, width - 1)
+        });
+    }
+    if let Some(n) = arity {
+        let rows: Vec<_> = rows
+            .iter()
+            .zip(&heads)
+            .map(|(row, x)| {
+                let mut expanded = match x {
+                    Expr::Comma(x) => x.items.iter().collect(),
+                    x => vec![*x; n],
+                };
+                expanded.extend(&row[1..]);
+                expanded
+            })
+            .collect();
+        return covers(&rows, n + width - 1);
+    }
+    let rows: Vec<_> = rows
+        .iter()
+        .zip(&heads)
+        .filter(|(_, x)| matches!(x, Expr::Identifier(_)))
+        .map(|(row, _)| row[1..].to_vec())
+        .collect();
+    covers(&rows, width - 1)
+}
+
+/// expression inside any parentheses around it
+fn unparen(mut x: &Expr) -> &Expr {
+    while let Expr::Paren(paren) = x {
+        x = &paren.expr;
+    }
+    x
+}
+
 impl std::fmt::Debug for FunctionCallExpr {
     #[cold]
     #[inline(never)]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut ds = f.debug_struct("FnCallExpr");
-        ds.field("name", &self.name).field("args", &self.args);
+        ds.field("callee", &self.callee).field("args", &self.args);
         ds.finish()
     }
 }
 
+#[derive(Debug, Clone, Hash)]
+pub struct UnaryExpr {
+    pub operator: String,
+    pub expr: Expr,
+}
+
 #[derive(Debug, Clone, Hash)]
 pub struct BinaryExpr {
     pub operator: String,
     pub lhs: Expr,
     pub rhs: Expr,
 }
+
+/// source file, its items are run in order in one module scope
+#[derive(Clone, Debug, Hash)]
+pub struct Program {
+    pub items: Vec<Item>,
+}
+
+#[derive(Clone, Debug, Hash)]
+pub enum Item {
+    Let(Box<LetItem>),
+    Expr(Expr),
+}
+
+/// `let name = value`, or `let rec name = value` and `def name params = body`
+/// which can refer to `name` itself
+#[derive(Clone, Debug, Hash)]
+pub struct LetItem {
+    /// text of the `///` comments before the item
+    pub doc: Option<String>,
+    pub name: String,
+    pub recursive: bool,
+    pub value: Expr,
+}
+
+/// `let name = value` or `let rec name = value`, without the doc
+impl std::fmt::Display for LetItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rec = if self.recursive { "rec " } else { "" };
+        write!(f, "let {}{} = {}", rec, self.name, self.value)
+    }
+}
+
+impl std::fmt::Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for item in &self.items {
+            match item {
+                Item::Let(x) => {
+                    for line in x.doc.iter().flat_map(|x| x.lines()) {
+                        writeln!(f, "/// {}", line)?;
+                    }
+                    writeln!(f, "{}", x)?;
+                }
+                Item::Expr(x) => writeln!(f, "{}", x)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(x: &str) -> Expr {
+        Expr::Identifier(x.to_string())
+    }
+
+    fn binary(operator: &str, lhs: Expr, rhs: Expr) -> Expr {
+        Expr::binary(operator, lhs, rhs).unwrap()
+    }
+
+    #[test]
+    fn knows_the_operators() {
+        assert!(matches!(binary("&&", name("a"), name("b")), Expr::And(_)));
+        assert!(matches!(binary(">>", name("a"), name("b")), Expr::Shr(_)));
+        assert!(matches!(Expr::unary("~", name("a")), Some(Expr::BitNot(_))));
+        assert!(Expr::binary("=", name("a"), name("b")).is_none());
+        assert!(Expr::unary("+", name("a")).is_none());
+    }
+
+    #[test]
+    fn prints_the_parentheses_the_grouping_needs() {
+        let (a, b, c) = (name("a"), name("b"), name("c"));
+        let x = binary("-", a.clone(), binary("-", b.clone(), c.clone()));
+        assert_eq!(x.to_string(), "a - (b - c)");
+        let x = binary("-", binary("-", a.clone(), b.clone()), c.clone());
+        assert_eq!(x.to_string(), "a - b - c");
+        let x = binary("**", binary("**", a.clone(), b.clone()), c.clone());
+        assert_eq!(x.to_string(), "(a ** b) ** c");
+        let x = binary("<", binary("<", a.clone(), b.clone()), c.clone());
+        assert_eq!(x.to_string(), "(a < b) < c");
+        let x = Expr::unary("-", binary("+", a.clone(), b.clone())).unwrap();
+        assert_eq!(x.to_string(), "-(a + b)");
+        let x = binary("**", Expr::Integer(-2), Expr::Integer(2));
+        assert_eq!(x.to_string(), "(-2) ** 2");
+        let x = binary("*", binary("||", a, b), c);
+        assert_eq!(x.to_string(), "(a || b) * c");
+    }
+
+    #[test]
+    fn prints_calls_of_any_callee() {
+        let (f, a, b) = (name("f"), name("a"), name("b"));
+        let function = Expr::Function(Box::new(FunctionExpr {
+            doc: None,
+            params: vec![name("x")],
+            body: binary("+", name("x"), Expr::Integer(1)),
+        }));
+        assert_eq!(Expr::call(function, vec![Expr::Integer(2)]).to_string(), "(x => x + 1) 2");
+        let curried = Expr::call(Expr::call(f.clone(), vec![a.clone()]), vec![b.clone()]);
+        assert_eq!(curried.to_string(), "f a b");
+        let x = Expr::call(f.clone(), vec![Expr::call(a.clone(), vec![b.clone()])]);
+        assert_eq!(x.to_string(), "f (a b)");
+        let x = Expr::call(f.clone(), vec![a.clone(), b.clone()]);
+        assert_eq!(x.to_string(), "f(a, b)");
+        let x = Expr::call(f.clone(), vec![Expr::tuple(vec![a.clone(), b.clone()])]);
+        assert_eq!(x.to_string(), "f (a, b)");
+        let x = Expr::call(Expr::Integer(-1), vec![Expr::unary("-", a).unwrap()]);
+        assert_eq!(x.to_string(), "(-1) (-a)");
+        assert_eq!(Expr::call(f, Vec::new()).to_string(), "f()");
+    }
+
+    #[test]
+    fn knows_which_matches_are_exhaustive() {
+        let arm = |pattern: Expr| MatchArm {
+            pattern,
+            guard: None,
+            body: Expr::Integer(0),
+        };
+        let exhaustive = |patterns: Vec<Expr>| {
+            let arms = patterns.into_iter().map(arm).collect();
+            let x = MatchExpr {
+                scrutinee: name("x"),
+                arms,
+            };
+            x.is_exhaustive()
+        };
+        let (t, f) = (Expr::Boolean(true), Expr::Boolean(false));
+        assert!(exhaustive(vec![t.clone(), f.clone()]));
+        assert!(!exhaustive(vec![t.clone()]));
+        assert!(!exhaustive(vec![Expr::Integer(1), Expr::Integer(-1)]));
+        assert!(exhaustive(vec![Expr::Integer(1), name("_")]));
+        let pair = |x: &Expr, y: &Expr| Expr::tuple(vec![x.clone(), y.clone()]);
+        assert!(exhaustive(vec![pair(&f, &name("_")), pair(&t, &name("n"))]));
+        assert!(!exhaustive(vec![pair(&t, &name("_")), pair(&name("_"), &f)]));
+        assert!(!exhaustive(Vec::new()));
+    }
+}
@@ -0,0 +1,154 @@
+/// arbitrary precision integer, for the literals which do not fit in an `i64`
+#[derive(Clone, Default, Eq, Hash, PartialEq)]
+pub struct BigInt {
+    negative: bool,
+    /// base `2^32` digits from the least significant one, without leading zeros
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    /// parse `digits` of `radix`, which must all be valid
+    pub fn from_digits(digits: &str, radix: u32) -> Option<BigInt> {
+        let mut x = BigInt::default();
+        for d in digits.chars() {
+            x.mul_add(radix, d.to_digit(radix)?);
+        }
+        Some(x)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        let magnitude = match self.magnitude.as_slice() {
+            [] => 0,
+            [x] => *x as u64,
+            [x, y] => *x as u64 | (*y as u64) << 32,
+            _ => return None,
+        };
+        match self.negative {
+            true if magnitude == 1 << 63 => Some(i64::MIN),
+            true => i64::try_from(magnitude).ok().map(|x| -x),
+            false => i64::try_from(magnitude).ok(),
+        }
+    }
+
+    /// `self * m + a` in place
+    fn mul_add(&mut self, m: u32, a: u32) {
+        let mut carry = a as u64;
+        for digit in self.magnitude.iter_mut() {
+            let x = *digit as u64 * m as u64 + carry;
+            *digit = x as u32;
+            carry = x >> 32;
+        }
+        if carry > 0 {
+            self.magnitude.push(carry as u32);
+        }
+    }
+
+    /// `self / d` in place, yields the remainder
+    fn div_rem(&mut self, d: u32) -> u32 {
+        let mut remainder = 0u64;
+        for digit in self.magnitude.iter_mut().rev() {
+            let x = remainder << 32 | *digit as u64;
+            *digit = (x / d as u64) as u32;
+            remainder = x % d as u64;
+        }
+        while self.magnitude.last() == Some(&0) {
+            self.magnitude.pop();
+        }
+        remainder as u32
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(x: i64) -> Self {
+        let magnitude = x.unsigned_abs();
+        let mut x = BigInt {
+            negative: x < 0,
+            magnitude: vec![magnitude as u32, (magnitude >> 32) as u32],
+        };
+        while x.magnitude.last() == Some(&0) {
+            x.magnitude.pop();
+        }
+        x
+    }
+}
+
+impl std::ops::Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> Self::Output {
+        BigInt {
+            negative: !self.negative && !self.is_zero(),
+            ..self
+        }
+    }
+}
+
+impl std::fmt::Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut x = self.clone();
+        let mut chunks = Vec::new();
+        while !x.is_zero() {
+            chunks.push(x.div_rem(1_000_000_000));
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        match chunks.pop() {
+            Some(head) => write!(f, "{}", head)?,
+            None => write!(f, "0")?,
+        }
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BigInt({})", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_digits_of_any_radix() {
+        let x = BigInt::from_digits("18446744073709551616", 10).unwrap();
+        assert_eq!(x.magnitude, [0, 0, 1]);
+        assert_eq!(x.to_string(), "18446744073709551616");
+        let x = BigInt::from_digits("ffffffffffffffffffff", 16).unwrap();
+        assert_eq!(x.to_string(), "1208925819614629174706175");
+        assert_eq!(BigInt::from_digits("12a", 10), None);
+        assert!(BigInt::from_digits("000", 10).unwrap().is_zero());
+    }
+
+    #[test]
+    fn converts_from_and_to_i64() {
+        for x in [0, 1, -1, i64::MAX, i64::MIN, 1 << 40] {
+            assert_eq!(BigInt::from(x).to_i64(), Some(x));
+            assert_eq!(BigInt::from(x).to_string(), x.to_string());
+        }
+        let x = BigInt::from_digits("9223372036854775808", 10).unwrap();
+        assert_eq!(x.to_i64(), None);
+        assert_eq!((-x).to_i64(), Some(i64::MIN));
+    }
+
+    #[test]
+    fn negates() {
+        let x = -BigInt::from_digits("100000000000000000000", 10).unwrap();
+        assert!(x.is_negative());
+        assert_eq!(format!("{:?}", x), "BigInt(-100000000000000000000)");
+        assert!(!(-BigInt::default()).is_negative());
+    }
+}
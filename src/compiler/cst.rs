@@ -0,0 +1,1221 @@
+use super::{
+    accelerator::{
+        binary_precedence, number_expr, quoted, right_precedence, Associativity, CHAINED,
+        PREFIX_PRECEDENCE,
+    },
+    ast::{
+        BlockExpr, Expr, Float, FunctionExpr, IfExpr, Item, LetItem, MatchArm, MatchExpr, Program,
+    },
+    parser::parseco::{err, err_at, ParserError},
+    tex::{TouchMeToken, TouchMeTokenBundle, TouchMeTokenCategory},
+    tokenizer::{doc_comment, normalize, number},
+};
+
+pub use super::ast::Span;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SyntaxKind {
+    /// the whole source, up to and including `EOF`, items separated by `;`
+    /// or `Newline`
+    Root,
+    /// `let name = value`, `let rec name = value` or `def name params = body`
+    Definition,
+    /// `x y => body`
+    Function,
+    /// patterns before the arrow of a function
+    Parameters,
+    /// `a, b`, items joined by `,`
+    Tuple,
+    /// `lhs op rhs`, e.g. `a + b`
+    Binary,
+    /// `op expr`, e.g. `-x`
+    Unary,
+    /// `f x` or `f(x, y)`, the callee and one argument or `Arguments`
+    Call,
+    /// `(x, y)` right after the callee
+    Arguments,
+    /// `( expr )`
+    Paren,
+    /// `if condition then consequent else alternative`
+    If,
+    /// `match scrutinee with`, then `Arm` nodes joined by `|`
+    Match,
+    /// items on the lines of an indented block, or joined by `;` in
+    /// parentheses, `Definition` nodes and then the result
+    Block,
+    /// `pattern => body` or `pattern if guard => body`
+    Arm,
+    Identifier,
+    Number,
+    String,
+    Boolean,
+    /// tokens which no rule matches
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(TouchMeToken),
+}
+
+/// node of the concrete syntax tree, which keeps every token of the source
+/// including whitespace and comments, so that it prints back byte for byte
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    fn new(kind: SyntaxKind) -> Self {
+        SyntaxNode {
+            kind,
+            children: Vec::new(),
+        }
+    }
+
+    /// child nodes
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|x| match x {
+            SyntaxElement::Node(x) => Some(x),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// child tokens, trivia included
+    pub fn tokens(&self) -> impl Iterator<Item = &TouchMeToken> {
+        self.children.iter().filter_map(|x| match x {
+            SyntaxElement::Token(x) => Some(x),
+            SyntaxElement::Node(_) => None,
+        })
+    }
+
+    /// tokens of the whole subtree in source order
+    pub fn descendant_tokens(&self) -> Vec<&TouchMeToken> {
+        let mut tokens = Vec::new();
+        for x in &self.children {
+            match x {
+                SyntaxElement::Token(x) => tokens.push(x),
+                SyntaxElement::Node(x) => tokens.extend(x.descendant_tokens()),
+            }
+        }
+        tokens
+    }
+
+    /// range of the subtree in the source, trivia included, the virtual
+    /// tokens of the layout mode have no range
+    pub fn span(&self) -> Span {
+        let mut tokens = self.descendant_tokens();
+        tokens.retain(|x| !is_layout(x));
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => Span {
+                start: first.position as usize,
+                end: (last.position + last.length) as usize,
+            },
+            _ => Span::default(),
+        }
+    }
+}
+
+impl std::fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for x in self.descendant_tokens() {
+            write!(f, "{}", x.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// whether the token is a virtual one of the layout mode
+fn is_layout(token: &TouchMeToken) -> bool {
+    matches!(
+        token.category,
+        TouchMeTokenCategory::Indent | TouchMeTokenCategory::Dedent | TouchMeTokenCategory::Newline
+    )
+}
+
+/// concrete syntax tree of `source`, which never fails, the tokens which no
+/// rule matches are kept in `Error` nodes
+pub fn parse(source: &str, filename: &str) -> SyntaxNode {
+    let tokens: Vec<_> = TouchMeTokenBundle::new(source, filename)
+        .with_trivia()
+        .with_layout()
+        .collect();
+    parse_tokens(&tokens)
+}
+
+/// concrete syntax tree of the tokens of `TouchMeTokenBundle::with_trivia`
+///
+/// in the layout mode a `Newline` ends an item, and a binary operator goes
+/// on to the next line only if that line is indented, e.g.
+/// ```text
+/// let x = 1
+///   - 2
+/// ```
+/// is one item, and `1` and `-2` on lines of their own are two; without the
+/// layout mode, as in brackets, line breaks are whitespace
+pub fn parse_tokens(tokens: &[TouchMeToken]) -> SyntaxNode {
+    let mut builder = Builder {
+        tokens,
+        index: 0,
+        functions: functions(tokens),
+        bars: bars(tokens),
+        in_arm: false,
+    };
+    let mut root = SyntaxNode::new(SyntaxKind::Root);
+    builder.items(&mut root, |_| false);
+    while builder.index < tokens.len() {
+        builder.bump(&mut root);
+    }
+    root
+}
+
+/// for each of `tokens`, whether patterns and an arrow start at it, found
+/// in a pass from the end which steps over the patterns in parentheses, so
+/// that `Builder::is_function` need not scan ahead
+fn functions(tokens: &[TouchMeToken]) -> Vec<bool> {
+    // the `)` of each `(`, and the number of tokens before each token which
+    // cannot be in a pattern
+    let mut closing = vec![None; tokens.len()];
+    let mut open = Vec::new();
+    let mut others = vec![0; tokens.len() + 1];
+    for (i, x) in tokens.iter().enumerate() {
+        let operator = match x.category {
+            TouchMeTokenCategory::Operator => x.canonical(),
+            _ => String::new(),
+        };
+        match operator.as_str() {
+            "(" => open.push(i),
+            ")" => {
+                if let Some(start) = open.pop() {
+                    closing[start] = Some(i);
+                }
+            }
+            _ => {}
+        }
+        let other = !x.is_trivia()
+            && x.category != TouchMeTokenCategory::Identifier
+            && !matches!(operator.as_str(), "(" | ")" | ",");
+        others[i + 1] = others[i] + other as usize;
+    }
+    let mut functions = vec![false; tokens.len() + 1];
+    for (i, x) in tokens.iter().enumerate().rev() {
+        functions[i] = match (x.category, closing[i]) {
+            _ if x.is_trivia() => functions[i + 1],
+            (TouchMeTokenCategory::Identifier, _) => functions[i + 1],
+            (_, Some(end)) => others[end] == others[i] && functions[end + 1],
+            (TouchMeTokenCategory::Operator, _) => {
+                matches!(x.canonical().as_str(), "->" | "=>")
+            }
+            _ => false,
+        };
+    }
+    functions
+}
+
+/// for each of `tokens`, whether it is a `|` which starts a match arm, i.e.
+/// is followed by a pattern and then `=>`, `->` or `if`, found in a pass
+/// from the end like `functions`
+fn bars(tokens: &[TouchMeToken]) -> Vec<bool> {
+    // whether patterns and then an arrow or `if` start at each token, and
+    // whether that is an arrow or `if` itself
+    let mut arms = vec![(false, false); tokens.len() + 1];
+    let mut bars = vec![false; tokens.len()];
+    for (i, x) in tokens.iter().enumerate().rev() {
+        let text = x.canonical();
+        arms[i] = match (x.category, text.as_str()) {
+            _ if x.is_trivia() => arms[i + 1],
+            (TouchMeTokenCategory::Operator, "=>" | "->")
+            | (TouchMeTokenCategory::Keyword, "if") => (true, true),
+            (
+                TouchMeTokenCategory::Identifier
+                | TouchMeTokenCategory::Number
+                | TouchMeTokenCategory::String,
+                _,
+            )
+            | (TouchMeTokenCategory::Keyword, "true" | "false")
+            | (TouchMeTokenCategory::Operator, "-" | "," | "(" | ")") => (arms[i + 1].0, false),
+            _ => (false, false),
+        };
+        bars[i] = x.category == TouchMeTokenCategory::Operator
+            && text == "|"
+            && arms[i + 1] == (true, false);
+    }
+    bars
+}
+
+/// recursive descent over tokens, trivia goes to the node of the token
+/// after it
+struct Builder<'t> {
+    tokens: &'t [TouchMeToken],
+    index: usize,
+    /// see `functions`
+    functions: Vec<bool>,
+    /// see `bars`
+    bars: Vec<bool>,
+    /// whether a `|` which starts an arm ends the expression, as it does in
+    /// the body of an arm but not in brackets inside it
+    in_arm: bool,
+}
+
+impl<'t> Builder<'t> {
+    /// next token other than trivia
+    fn peek(&self) -> Option<&'t TouchMeToken> {
+        self.tokens[self.index..].iter().find(|x| !x.is_trivia())
+    }
+
+    fn at(&self, category: TouchMeTokenCategory, value: &str) -> bool {
+        self.peek()
+            .is_some_and(|x| x.category == category && x.canonical() == value)
+    }
+
+    /// token other than trivia after the next one
+    fn peek_second(&self) -> Option<&'t TouchMeToken> {
+        self.tokens[self.index..]
+            .iter()
+            .filter(|x| !x.is_trivia())
+            .nth(1)
+    }
+
+    fn is(&self, category: TouchMeTokenCategory) -> bool {
+        self.peek().is_some_and(|x| x.category == category)
+    }
+
+    /// whether the next token, or the one after an `Indent` or a `Newline`
+    /// before it, is a `|` which ends the body of an arm
+    fn at_bar(&self) -> bool {
+        let mut next = (self.index..self.tokens.len()).filter(|&i| !self.tokens[i].is_trivia());
+        let index = match next.next() {
+            Some(i)
+                if matches!(
+                    self.tokens[i].category,
+                    TouchMeTokenCategory::Indent | TouchMeTokenCategory::Newline
+                ) =>
+            {
+                next.next()
+            }
+            x => x,
+        };
+        self.in_arm && index.is_some_and(|i| self.bars[i])
+    }
+
+    /// `parse` in brackets, where a `|` does not end the body of an arm
+    fn bracketed(&mut self, parse: fn(&mut Self) -> SyntaxNode) -> SyntaxNode {
+        let in_arm = std::mem::replace(&mut self.in_arm, false);
+        let node = parse(self);
+        self.in_arm = in_arm;
+        node
+    }
+
+    /// whether `next` holds for the next token, or for the token after an
+    /// `Indent` or a `Newline` before it, which then go into `node`
+    ///
+    /// `indented` counts the `Indent` taken, a `Newline` is taken in such a
+    /// block, or else if `same_line` allows a line of the same indentation
+    fn continues(
+        &mut self,
+        node: &mut SyntaxNode,
+        indented: &mut usize,
+        same_line: bool,
+        next: impl Fn(&TouchMeToken) -> bool,
+    ) -> bool {
+        let after = self.peek_second().is_some_and(&next);
+        if self.is(TouchMeTokenCategory::Indent) && after {
+            self.bump(node);
+            *indented += 1;
+            return true;
+        }
+        if self.is(TouchMeTokenCategory::Newline) && after && (same_line || *indented > 0) {
+            self.bump(node);
+            return true;
+        }
+        self.peek().is_some_and(next)
+    }
+
+    /// take the `Dedent` of each `Indent` which `continues` has taken
+    fn dedent(&mut self, node: &mut SyntaxNode, indented: usize) {
+        for _ in 0..indented {
+            if !self.is(TouchMeTokenCategory::Dedent) {
+                break;
+            }
+            self.bump(node);
+        }
+    }
+
+    /// whether the input or the tokens before `end` run out
+    fn is_end(&self, end: fn(&Self) -> bool) -> bool {
+        end(self)
+            || !self
+                .peek()
+                .is_some_and(|x| x.category != TouchMeTokenCategory::EOF)
+    }
+
+    /// items separated by `;` or `Newline` up to `end`, the tokens left
+    /// before it go into an `Error` node
+    fn items(&mut self, node: &mut SyntaxNode, end: fn(&Self) -> bool) {
+        while !self.is_end(end) {
+            let index = self.index;
+            let item = match self.is_definition() {
+                true => self.definition(),
+                false => self.expr(),
+            };
+            node.children.push(SyntaxElement::Node(item));
+            if self.index == index {
+                break;
+            }
+            if self.at(TouchMeTokenCategory::Operator, ";") {
+                self.bump(node);
+                if self.is(TouchMeTokenCategory::Newline) {
+                    self.bump(node);
+                }
+            } else if self.is(TouchMeTokenCategory::Newline) {
+                self.bump(node);
+            } else {
+                break;
+            }
+        }
+        if self.is_end(end) {
+            return;
+        }
+        let mut error = SyntaxNode::new(SyntaxKind::Error);
+        let mut depth = 0_usize;
+        while depth > 0 || !self.is_end(end) {
+            let x = self.peek().map(|x| (x.category, x.canonical()));
+            match x.as_ref().map(|(x, y)| (*x, y.as_str())) {
+                Some((TouchMeTokenCategory::Indent, _))
+                | Some((TouchMeTokenCategory::Operator, "(" | "[")) => depth += 1,
+                Some((TouchMeTokenCategory::Dedent, _))
+                | Some((TouchMeTokenCategory::Operator, ")" | "]")) => {
+                    depth = depth.saturating_sub(1)
+                }
+                Some((TouchMeTokenCategory::EOF, _)) | None => break,
+                _ => {}
+            }
+            self.bump(&mut error);
+        }
+        node.children.push(SyntaxElement::Node(error));
+    }
+
+    /// `Indent`, the items of the block and its `Dedent`
+    fn block(&mut self) -> SyntaxNode {
+        let mut node = SyntaxNode::new(SyntaxKind::Block);
+        self.bump(&mut node);
+        self.items(&mut node, |x| x.is(TouchMeTokenCategory::Dedent));
+        if self.is(TouchMeTokenCategory::Dedent) {
+            self.bump(&mut node);
+        }
+        node
+    }
+
+    /// `(`, items joined by `;` and `)`
+    fn paren_block(&mut self) -> SyntaxNode {
+        let mut node = SyntaxNode::new(SyntaxKind::Block);
+        self.bump(&mut node);
+        self.items(&mut node, |x| x.at(TouchMeTokenCategory::Operator, ")"));
+        if self.at(TouchMeTokenCategory::Operator, ")") {
+            self.bump(&mut node);
+        }
+        node
+    }
+
+    fn is_definition(&self) -> bool {
+        self.at(TouchMeTokenCategory::Keyword, "let")
+            || self.at(TouchMeTokenCategory::Keyword, "def")
+    }
+
+    /// `let` or `def`, the name, the parameters of `def`, `=` and the value
+    fn definition(&mut self) -> SyntaxNode {
+        let mut node = SyntaxNode::new(SyntaxKind::Definition);
+        let def = self.at(TouchMeTokenCategory::Keyword, "def");
+        self.bump(&mut node);
+        if !def && self.at(TouchMeTokenCategory::Keyword, "rec") {
+            self.bump(&mut node);
+        }
+        let name = self.pattern();
+        node.children.push(SyntaxElement::Node(name));
+        if def {
+            let mut parameters = SyntaxNode::new(SyntaxKind::Parameters);
+            while self.is(TouchMeTokenCategory::Identifier)
+                || self.at(TouchMeTokenCategory::Operator, "(")
+            {
+                parameters
+                    .children
+                    .push(SyntaxElement::Node(self.pattern()));
+            }
+            node.children.push(SyntaxElement::Node(parameters));
+        }
+        if self.at(TouchMeTokenCategory::Operator, "=") {
+            self.bump(&mut node);
+            node.children.push(SyntaxElement::Node(self.expr()));
+        }
+        node
+    }
+
+    /// move the trivia and the token after it into `node`
+    fn bump(&mut self, node: &mut SyntaxNode) {
+        while let Some(x) = self.tokens.get(self.index) {
+            self.index += 1;
+            node.children.push(SyntaxElement::Token(x.clone()));
+            if !x.is_trivia() {
+                break;
+            }
+        }
+    }
+
+    /// whether patterns and an arrow come next
+    fn is_function(&self) -> bool {
+        (self.is(TouchMeTokenCategory::Identifier) || self.at(TouchMeTokenCategory::Operator, "("))
+            && self.functions[self.index]
+    }
+
+    fn function(&mut self) -> SyntaxNode {
+        let mut node = SyntaxNode::new(SyntaxKind::Function);
+        let mut parameters = SyntaxNode::new(SyntaxKind::Parameters);
+        while self.is(TouchMeTokenCategory::Identifier)
+            || self.at(TouchMeTokenCategory::Operator, "(")
+        {
+            parameters
+                .children
+                .push(SyntaxElement::Node(self.pattern()));
+        }
+        node.children.push(SyntaxElement::Node(parameters));
+        self.bump(&mut node);
+        node.children.push(SyntaxElement::Node(self.item()));
+        node
+    }
+
+    /// a name, or patterns in parentheses
+    fn pattern(&mut self) -> SyntaxNode {
+        self.pattern_with(Self::pattern)
+    }
+
+    /// a literal, which may be a negative number, or a pattern whose items
+    /// are patterns of a match arm
+    fn match_pattern(&mut self) -> SyntaxNode {
+        let negative = self.at(TouchMeTokenCategory::Operator, "-")
+            && self
+                .peek_second()
+                .is_some_and(|x| x.category == TouchMeTokenCategory::Number);
+        if negative {
+            let mut node = SyntaxNode::new(SyntaxKind::Unary);
+            self.bump(&mut node);
+            node.children.push(SyntaxElement::Node(self.primary()));
+            return node;
+        }
+        match self.peek().map(|x| x.category) {
+            Some(
+                TouchMeTokenCategory::Number
+                | TouchMeTokenCategory::String
+                | TouchMeTokenCategory::Keyword,
+            ) => self.primary(),
+            _ => self.pattern_with(Self::match_pattern),
+        }
+    }
+
+    /// a name, or `item` patterns in parentheses
+    fn pattern_with(&mut self, item: fn(&mut Self) -> SyntaxNode) -> SyntaxNode {
+        if !self.at(TouchMeTokenCategory::Operator, "(") {
+            let kind = match self.peek().map(|x| x.category) {
+                Some(TouchMeTokenCategory::Identifier) => SyntaxKind::Identifier,
+                Some(TouchMeTokenCategory::EOF) | None => {
+                    return SyntaxNode::new(SyntaxKind::Error)
+                }
+                Some(_) => SyntaxKind::Error,
+            };
+            let mut node = SyntaxNode::new(kind);
+            self.bump(&mut node);
+            return node;
+        }
+        let mut node = SyntaxNode::new(SyntaxKind::Paren);
+        self.bump(&mut node);
+        let first = item(self);
+        let inner = self.tuple(first, item);
+        node.children.push(SyntaxElement::Node(inner));
+        if self.at(TouchMeTokenCategory::Operator, ")") {
+            self.bump(&mut node);
+        }
+        node
+    }
+
+    /// `first` and the items after it joined by `,`, or `first` alone
+    fn tuple(&mut self, first: SyntaxNode, item: fn(&mut Self) -> SyntaxNode) -> SyntaxNode {
+        if !self.at(TouchMeTokenCategory::Operator, ",") {
+            return first;
+        }
+        let mut node = SyntaxNode::new(SyntaxKind::Tuple);
+        node.children.push(SyntaxElement::Node(first));
+        while self.at(TouchMeTokenCategory::Operator, ",") {
+            self.bump(&mut node);
+            node.children.push(SyntaxElement::Node(item(self)));
+        }
+        node
+    }
+
+    /// items joined by `,` into a tuple, or a single item
+    fn expr(&mut self) -> SyntaxNode {
+        let item = self.item();
+        self.tuple(item, Self::item)
+    }
+
+    /// a function or an expression other than a tuple
+    fn item(&mut self) -> SyntaxNode {
+        if self.at(TouchMeTokenCategory::Keyword, "if") {
+            return self.conditional();
+        }
+        if self.at(TouchMeTokenCategory::Keyword, "match") {
+            return self.match_expr();
+        }
+        match self.is_function() {
+            true => self.function(),
+            false => self.binary(0),
+        }
+    }
+
+    /// `then` and `else` may start lines of their own
+    fn conditional(&mut self) -> SyntaxNode {
+        let mut node = SyntaxNode::new(SyntaxKind::If);
+        self.bump(&mut node);
+        node.children.push(SyntaxElement::Node(self.item()));
+        let mut indented = 0;
+        for keyword in ["then", "else"] {
+            let keyword = |x: &TouchMeToken| {
+                x.category == TouchMeTokenCategory::Keyword && x.value == keyword
+            };
+            if !self.continues(&mut node, &mut indented, true, keyword) {
+                break;
+            }
+            self.bump(&mut node);
+            node.children.push(SyntaxElement::Node(self.item()));
+        }
+        self.dedent(&mut node, indented);
+        node
+    }
+
+    fn match_expr(&mut self) -> SyntaxNode {
+        let mut node = SyntaxNode::new(SyntaxKind::Match);
+        self.bump(&mut node);
+        node.children.push(SyntaxElement::Node(self.item()));
+        if !self.at(TouchMeTokenCategory::Keyword, "with") {
+            return node;
+        }
+        self.bump(&mut node);
+        let bar =
+            |x: &TouchMeToken| x.category == TouchMeTokenCategory::Operator && x.canonical() == "|";
+        let mut indented = 0;
+        if self.continues(&mut node, &mut indented, false, bar) {
+            self.bump(&mut node);
+        }
+        let in_arm = std::mem::replace(&mut self.in_arm, true);
+        loop {
+            node.children.push(SyntaxElement::Node(self.arm()));
+            if !self.continues(&mut node, &mut indented, true, bar) {
+                break;
+            }
+            self.bump(&mut node);
+        }
+        self.in_arm = in_arm;
+        self.dedent(&mut node, indented);
+        node
+    }
+
+    /// the body goes on up to a `|` which starts the next arm, see `bars`
+    fn arm(&mut self) -> SyntaxNode {
+        let mut node = SyntaxNode::new(SyntaxKind::Arm);
+        let first = self.match_pattern();
+        let pattern = self.tuple(first, Self::match_pattern);
+        node.children.push(SyntaxElement::Node(pattern));
+        if self.at(TouchMeTokenCategory::Keyword, "if") {
+            self.bump(&mut node);
+            node.children.push(SyntaxElement::Node(self.binary(0)));
+        }
+        if self.at(TouchMeTokenCategory::Operator, "=>")
+            || self.at(TouchMeTokenCategory::Operator, "->")
+        {
+            self.bump(&mut node);
+            node.children.push(SyntaxElement::Node(self.expr()));
+        }
+        node
+    }
+
+    /// canonical text of the next token if it is an operator
+    fn operator(&self) -> Option<String> {
+        self.peek()
+            .filter(|x| x.category == TouchMeTokenCategory::Operator)
+            .map(TouchMeToken::canonical)
+    }
+
+    /// operands joined by the binary operators which bind at least as tight
+    /// as `min`, by precedence climbing, a chained comparison is kept and
+    /// left to `lower`
+    ///
+    /// an operator starts a line only in an indented one, see `parse_tokens`,
+    /// and a `|` ends the body of an arm if an arm starts at it
+    fn binary(&mut self, min: u8) -> SyntaxNode {
+        let mut lhs = self.unary();
+        let mut indented = 0;
+        loop {
+            let binds = |x: &TouchMeToken| {
+                x.category == TouchMeTokenCategory::Operator
+                    && binary_precedence(&x.canonical()).is_some_and(|x| x.0 >= min)
+            };
+            let mut layout = SyntaxNode::new(SyntaxKind::Binary);
+            if self.at_bar() || !self.continues(&mut layout, &mut indented, false, binds) {
+                break;
+            }
+            let mut node = SyntaxNode::new(SyntaxKind::Binary);
+            node.children.push(SyntaxElement::Node(lhs));
+            node.children.append(&mut layout.children);
+            let operator = self.operator().unwrap_or_default();
+            let (precedence, associativity) =
+                binary_precedence(&operator).unwrap_or((min, Associativity::Left));
+            self.bump(&mut node);
+            let rhs = self.binary(right_precedence(precedence, associativity));
+            node.children.push(SyntaxElement::Node(rhs));
+            lhs = node;
+        }
+        self.dedent(&mut lhs, indented);
+        lhs
+    }
+
+    fn unary(&mut self) -> SyntaxNode {
+        match self.operator().as_deref() {
+            Some("-" | "!" | "~") => {
+                let mut node = SyntaxNode::new(SyntaxKind::Unary);
+                self.bump(&mut node);
+                let operand = self.binary(PREFIX_PRECEDENCE);
+                node.children.push(SyntaxElement::Node(operand));
+                node
+            }
+            _ => self.call(),
+        }
+    }
+
+    /// whether the next token may start an argument of `f x y`
+    fn is_argument(&self) -> bool {
+        self.peek().is_some_and(|x| match x.category {
+            TouchMeTokenCategory::Identifier
+            | TouchMeTokenCategory::Number
+            | TouchMeTokenCategory::String => true,
+            TouchMeTokenCategory::Operator => x.canonical() == "(",
+            TouchMeTokenCategory::Keyword => x.value == "true" || x.value == "false",
+            _ => false,
+        })
+    }
+
+    /// curried application, `f x y` is a `Call` in a `Call`
+    fn call(&mut self) -> SyntaxNode {
+        let mut callee = self.primary();
+        while callee.kind != SyntaxKind::Error {
+            let end = callee.span().end as u32;
+            let touching = self.at(TouchMeTokenCategory::Operator, "(")
+                && self.peek().is_some_and(|x| x.position == end);
+            if !touching && !self.is_argument() {
+                break;
+            }
+            let mut node = SyntaxNode::new(SyntaxKind::Call);
+            node.children.push(SyntaxElement::Node(callee));
+            let argument = match touching {
+                true => self.bracketed(Self::arguments),
+                false => self.primary(),
+            };
+            node.children.push(SyntaxElement::Node(argument));
+            callee = node;
+        }
+        callee
+    }
+
+    fn arguments(&mut self) -> SyntaxNode {
+        let mut node = SyntaxNode::new(SyntaxKind::Arguments);
+        self.bump(&mut node);
+        while !self.at(TouchMeTokenCategory::Operator, ")") {
+            node.children.push(SyntaxElement::Node(self.item()));
+            if !self.at(TouchMeTokenCategory::Operator, ",") {
+                break;
+            }
+            self.bump(&mut node);
+        }
+        if self.at(TouchMeTokenCategory::Operator, ")") {
+            self.bump(&mut node);
+        }
+        node
+    }
+
+    fn paren(&mut self) -> SyntaxNode {
+        let mut node = SyntaxNode::new(SyntaxKind::Paren);
+        self.bump(&mut node);
+        node.children.push(SyntaxElement::Node(self.expr()));
+        if self.at(TouchMeTokenCategory::Operator, ")") {
+            self.bump(&mut node);
+        }
+        node
+    }
+
+    fn primary(&mut self) -> SyntaxNode {
+        let kind = match self.peek().map(|x| x.category) {
+            Some(TouchMeTokenCategory::Indent) => return self.bracketed(Self::block),
+            _ if self.at(TouchMeTokenCategory::Operator, "(")
+                && self.peek_second().is_some_and(|x| {
+                    x.category == TouchMeTokenCategory::Keyword
+                        && matches!(x.value.as_str(), "let" | "def")
+                }) =>
+            {
+                return self.bracketed(Self::paren_block)
+            }
+            _ if self.at(TouchMeTokenCategory::Operator, "(") => {
+                return self.bracketed(Self::paren)
+            }
+            Some(TouchMeTokenCategory::Identifier) => SyntaxKind::Identifier,
+            Some(TouchMeTokenCategory::Number) => SyntaxKind::Number,
+            Some(TouchMeTokenCategory::String) => SyntaxKind::String,
+            Some(TouchMeTokenCategory::Keyword) if self.is_argument() => SyntaxKind::Boolean,
+            Some(TouchMeTokenCategory::EOF) | None => return SyntaxNode::new(SyntaxKind::Error),
+            Some(_) => SyntaxKind::Error,
+        };
+        let mut node = SyntaxNode::new(kind);
+        self.bump(&mut node);
+        node
+    }
+}
+
+/// error of an empty `Error` node, which the builder leaves only at the end
+/// of the input
+const END: &str = "unexpected end of input";
+
+/// `x` with the error of an empty `Error` node located at the `EOF` of `root`
+fn at_end<X>(root: &SyntaxNode, x: Result<X, ParserError>) -> Result<X, ParserError> {
+    let eof = root
+        .tokens()
+        .find(|x| x.category == TouchMeTokenCategory::EOF);
+    match (x, eof) {
+        (Err(e), Some(eof)) if e.message == END => err_at(END, eof.locator()),
+        (x, _) => x,
+    }
+}
+
+/// error `message` at the first token of `node`, a node without tokens
+/// is where the input ran out
+fn fail<X>(node: &SyntaxNode, message: &str) -> Result<X, ParserError> {
+    let tokens = node.descendant_tokens();
+    match tokens.into_iter().find(|x| !x.is_trivia()) {
+        Some(x) => err_at(message, x.locator()),
+        None => err(END),
+    }
+}
+
+/// canonical text of the operator of a `Binary` or a `Unary` node
+fn operator_of(node: &SyntaxNode) -> String {
+    let token = node
+        .tokens()
+        .find(|x| x.category == TouchMeTokenCategory::Operator);
+    token.map(TouchMeToken::canonical).unwrap_or_default()
+}
+
+/// `Expr` of a concrete syntax tree, a pass of its own so that tools can
+/// work on the tree without lowering it
+pub fn lower(node: &SyntaxNode) -> Result<Expr, ParserError> {
+    let nodes: Vec<_> = node.nodes().collect();
+    let token = node.tokens().find(|x| !x.is_trivia());
+    let value = token.map(|x| x.value.as_str()).unwrap_or_default();
+    match (node.kind, nodes.as_slice()) {
+        (SyntaxKind::Root, []) => fail(node, "expected an expression"),
+        (SyntaxKind::Root, [x, rest @ ..]) => {
+            let x = at_end(node, lower(x))?;
+            match rest {
+                [] => Ok(x),
+                [error] if error.kind == SyntaxKind::Error => lower(error),
+                [x, ..] => fail(x, "expected a single expression, not a program"),
+            }
+        }
+        (SyntaxKind::Definition, _) => fail(node, "expected an expression, not a definition"),
+        (SyntaxKind::Function, [parameters, body]) => {
+            let params = parameters.nodes().map(pattern).collect::<Result<_, _>>()?;
+            Ok(Expr::Function(Box::new(FunctionExpr {
+                doc: doc(parameters),
+                params,
+                body: lower(body)?,
+            })))
+        }
+        (SyntaxKind::Binary, [lhs, rhs]) => {
+            let operator = operator_of(node);
+            // after the left operand, whose errors come first in the source
+            let lhs_expr = lower(lhs)?;
+            let precedence = binary_precedence(&operator);
+            let chained = lhs.kind == SyntaxKind::Binary
+                && precedence.is_some_and(|x| x.1 == Associativity::None)
+                && binary_precedence(&operator_of(lhs)) == precedence;
+            let at = node
+                .tokens()
+                .find(|x| x.category == TouchMeTokenCategory::Operator);
+            if let (true, Some(at)) = (chained, at) {
+                return err_at(&format!("expected {}", CHAINED), at.locator());
+            }
+            match Expr::binary(&operator, lhs_expr, lower(rhs)?) {
+                Some(x) => Ok(x),
+                None => fail(node, &format!("`{}` is not a binary operator", operator)),
+            }
+        }
+        (SyntaxKind::Unary, [expr]) => {
+            let operator = operator_of(node);
+            match Expr::unary(&operator, lower(expr)?) {
+                Some(x) => Ok(x),
+                None => fail(node, &format!("`{}` is not a prefix operator", operator)),
+            }
+        }
+        (SyntaxKind::Call, [callee, x]) if x.kind == SyntaxKind::Arguments => {
+            let callee = lower(callee)?;
+            let args = x.nodes().map(lower).collect::<Result<_, _>>()?;
+            let closed = x.tokens().filter(|x| !x.is_trivia()).last();
+            if closed.map(TouchMeToken::canonical).as_deref() != Some(")") {
+                return fail(x, "expected `)` to close the arguments");
+            }
+            Ok(Expr::call(callee, args))
+        }
+        (SyntaxKind::Call, [callee, x]) => Ok(Expr::call(lower(callee)?, vec![lower(x)?])),
+        (SyntaxKind::Tuple, items) => Ok(Expr::tuple(
+            items.iter().map(|x| lower(x)).collect::<Result<_, _>>()?,
+        )),
+        (SyntaxKind::Paren, [expr]) if node.tokens().filter(|x| !x.is_trivia()).count() == 2 => {
+            let mut parens = node.tokens().filter(|x| !x.is_trivia());
+            let (open, close) = (parens.next(), parens.next());
+            let span = match (open, close) {
+                (Some(open), Some(close)) => Span {
+                    start: open.position as usize,
+                    end: (close.position + close.length) as usize,
+                },
+                _ => Span::default(),
+            };
+            Ok(Expr::paren(lower(expr)?, span))
+        }
+        (SyntaxKind::Paren, _) => fail(node, "expected `)` to close `(`"),
+        (SyntaxKind::If, [condition, consequent, alternative]) => Ok(Expr::If(Box::new(IfExpr {
+            condition: lower(condition)?,
+            consequent: lower(consequent)?,
+            alternative: lower(alternative)?,
+        }))),
+        (SyntaxKind::If, _) => fail(node, "expected `then` and `else` in the conditional"),
+        (SyntaxKind::Match, [scrutinee, arms @ ..]) if !arms.is_empty() => {
+            let x = MatchExpr {
+                scrutinee: lower(scrutinee)?,
+                arms: arms.iter().map(|x| arm(x)).collect::<Result<_, _>>()?,
+            };
+            match x.is_exhaustive() {
+                true => Ok(Expr::Match(Box::new(x))),
+                false => fail(node, "expected an arm of `_`, the match is not exhaustive"),
+            }
+        }
+        (SyntaxKind::Match, _) => fail(node, "expected `with` and arms in the match"),
+        (SyntaxKind::Block, _) => block(node),
+        (SyntaxKind::Arm, _) => fail(node, "expected an expression, not a match arm"),
+        (SyntaxKind::Identifier, []) => Ok(Expr::Identifier(normalize(value))),
+        (SyntaxKind::Boolean, []) => Ok(Expr::Boolean(value == "true")),
+        (SyntaxKind::Number, []) => match number(value) {
+            Ok((_, x)) => Ok(number_expr(x)),
+            Err(e) => fail(node, &e.to_string()),
+        },
+        (SyntaxKind::String, []) => match token {
+            Some(x) => quoted(value, x.position as usize, x.locator()),
+            None => fail(node, "expected a string"),
+        },
+        (SyntaxKind::Error, _) => match token {
+            Some(x) => {
+                let unexpected = match x.category {
+                    TouchMeTokenCategory::Indent => "indentation".to_string(),
+                    TouchMeTokenCategory::Dedent => "end of the indented block".to_string(),
+                    TouchMeTokenCategory::Newline => "line break".to_string(),
+                    _ => format!("`{}`", x.value),
+                };
+                err_at(&format!("unexpected {}", unexpected), x.locator())
+            }
+            None => err(END),
+        },
+        (kind, _) => fail(node, &format!("malformed {:?} node", kind)),
+    }
+}
+
+/// parameter of a function in a `Parameters` node
+fn pattern(node: &SyntaxNode) -> Result<Expr, ParserError> {
+    let nodes: Vec<_> = node.nodes().collect();
+    match (node.kind, nodes.as_slice()) {
+        (SyntaxKind::Identifier, []) => lower(node),
+        (SyntaxKind::Paren, [x]) if node.tokens().filter(|x| !x.is_trivia()).count() == 2 => {
+            pattern(x)
+        }
+        (SyntaxKind::Paren, _) => fail(node, "expected `)` to close `(`"),
+        (SyntaxKind::Tuple, items) => Ok(Expr::tuple(
+            items.iter().map(|x| pattern(x)).collect::<Result<_, _>>()?,
+        )),
+        _ => fail(node, "expected a parameter"),
+    }
+}
+
+/// `Program` of the root of a concrete syntax tree
+pub fn lower_program(root: &SyntaxNode) -> Result<Program, ParserError> {
+    let mut items = Vec::new();
+    for x in root.nodes() {
+        let item = match x.kind {
+            SyntaxKind::Definition => at_end(root, definition(x)).map(|x| Item::Let(Box::new(x))),
+            _ => at_end(root, lower(x)).map(Item::Expr),
+        };
+        items.push(item?);
+    }
+    Ok(Program { items })
+}
+
+/// text of the doc comments before the first token of `node`
+fn doc(node: &SyntaxNode) -> Option<String> {
+    let tokens = node.descendant_tokens();
+    let comments = tokens.into_iter().take_while(|x| x.is_trivia());
+    let doc: Vec<_> = comments
+        .filter_map(|x| doc_comment(&x.value).ok())
+        .map(|(_, x)| x)
+        .collect();
+    (!doc.is_empty()).then(|| doc.join("\n"))
+}
+
+/// `MatchArm` of an `Arm` node
+fn arm(node: &SyntaxNode) -> Result<MatchArm, ParserError> {
+    let arrow = node
+        .tokens()
+        .any(|x| matches!(x.canonical().as_str(), "=>" | "->"));
+    let nodes: Vec<_> = node.nodes().collect();
+    let (pattern, guard, body) = match nodes.as_slice() {
+        [pattern, body] if arrow => (pattern, None, body),
+        [pattern, guard, body] if arrow => (pattern, Some(lower(guard)?), body),
+        _ => return fail(node, "expected `=>` in the match arm"),
+    };
+    Ok(MatchArm {
+        pattern: match_pattern(pattern)?,
+        guard,
+        body: lower(body)?,
+    })
+}
+
+/// pattern of a match arm in an `Arm` node
+fn match_pattern(node: &SyntaxNode) -> Result<Expr, ParserError> {
+    let nodes: Vec<_> = node.nodes().collect();
+    match (node.kind, nodes.as_slice()) {
+        (
+            SyntaxKind::Identifier | SyntaxKind::Number | SyntaxKind::String | SyntaxKind::Boolean,
+            [],
+        ) => lower(node),
+        (SyntaxKind::Unary, [x]) if x.kind == SyntaxKind::Number => match lower(x)? {
+            Expr::Integer(x) => Ok(Expr::Integer(-x)),
+            Expr::BigInteger(x) => {
+                let x = -x;
+                Ok(x.to_i64().map_or(Expr::BigInteger(x), Expr::Integer))
+            }
+            Expr::Float(x) => Ok(Expr::Float(Float(-x.0))),
+            _ => fail(node, "expected a number after `-`"),
+        },
+        (SyntaxKind::Paren, [x]) if node.tokens().filter(|x| !x.is_trivia()).count() == 2 => {
+            match_pattern(x)
+        }
+        (SyntaxKind::Paren, _) => fail(node, "expected `)` to close `(`"),
+        (SyntaxKind::Tuple, items) => Ok(Expr::tuple(
+            items
+                .iter()
+                .map(|x| match_pattern(x))
+                .collect::<Result<_, _>>()?,
+        )),
+        _ => fail(node, "expected a pattern"),
+    }
+}
+
+/// `LetItem` of a `Definition` node
+fn definition(node: &SyntaxNode) -> Result<LetItem, ParserError> {
+    let keywords: Vec<_> = node
+        .tokens()
+        .filter(|x| x.category == TouchMeTokenCategory::Keyword)
+        .map(|x| x.value.as_str())
+        .collect();
+    if !node.tokens().any(|x| x.canonical() == "=") {
+        return fail(node, "expected `=` in the definition");
+    }
+    let nodes: Vec<_> = node.nodes().collect();
+    let (name, params, value) = match nodes.as_slice() {
+        [name, value] => (name, Vec::new(), value),
+        [name, parameters, value] => {
+            let params = parameters.nodes().map(pattern).collect::<Result<_, _>>()?;
+            (name, params, value)
+        }
+        _ => return fail(node, "malformed Definition node"),
+    };
+    let name = match (name.kind, name.tokens().find(|x| !x.is_trivia())) {
+        (SyntaxKind::Identifier, Some(x)) => normalize(&x.value),
+        _ => return fail(node, "expected a name to define"),
+    };
+    let body = lower(value)?;
+    let value = match params.is_empty() {
+        true => body,
+        false => Expr::Function(Box::new(FunctionExpr {
+            doc: None,
+            params,
+            body,
+        })),
+    };
+    Ok(LetItem {
+        doc: doc(node),
+        name,
+        recursive: keywords.contains(&"rec") || keywords.contains(&"def"),
+        value,
+    })
+}
+
+/// `Expr` of a `Block` node, the result itself if no items come before it
+fn block(node: &SyntaxNode) -> Result<Expr, ParserError> {
+    let mut tokens = node.tokens().filter(|x| !x.is_trivia());
+    let open = tokens.next().map(TouchMeToken::canonical);
+    let close = tokens.last().map(TouchMeToken::canonical);
+    if open.as_deref() == Some("(") && close.as_deref() != Some(")") {
+        return fail(node, "expected `)` to close `(`");
+    }
+    let nodes: Vec<_> = node.nodes().collect();
+    if let Some(x) = nodes.iter().find(|x| x.kind == SyntaxKind::Error) {
+        return lower(x);
+    }
+    let (result, items) = match nodes.split_last() {
+        Some((result, _)) if result.kind == SyntaxKind::Definition => {
+            return fail(
+                result,
+                "expected the result of the block after this definition",
+            )
+        }
+        Some(x) => x,
+        None => return fail(node, "expected an expression in the block"),
+    };
+    let items = items
+        .iter()
+        .map(|x| match x.kind {
+            SyntaxKind::Definition => definition(x),
+            _ => fail(
+                x,
+                "expected a definition, only the last item of a block is its result",
+            ),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let result = lower(result)?;
+    match items.is_empty() {
+        true => Ok(result),
+        false => Ok(Expr::Block(Box::new(BlockExpr { items, result }))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCES: [&str; 6] = [
+        "a + b * -c",
+        "  /// twice\n  x =>\n    // local\n    x + x\n",
+        "(a) + \"${ 1 + 2 }\" // trailing",
+        "a ) junk (\n  b\n",
+        "match x with\n  | -1 => 1\n  | (_, n) if n > 0 => 2 | _ => 3\nif c\nthen a\nelse b",
+        "  /// square\n  def square x =\n    // local\n    let y = x\n    y * y ;\n square 3\n",
+    ];
+
+    #[test]
+    fn prints_back_byte_for_byte() {
+        for source in SOURCES {
+            assert_eq!(parse(source, "test").to_string(), source);
+        }
+    }
+
+    #[test]
+    fn nodes_expose_spans_and_tokens() {
+        let root = parse("// c\nf + (a + b)", "test");
+        let binary = root.nodes().next().unwrap();
+        assert_eq!(binary.kind, SyntaxKind::Binary);
+        assert_eq!(binary.span(), Span { start: 0, end: 16 });
+        let paren = binary.nodes().nth(1).unwrap();
+        assert_eq!(paren.kind, SyntaxKind::Paren);
+        assert_eq!(paren.span(), Span { start: 8, end: 16 });
+        let tokens: Vec<_> = paren.tokens().map(|x| x.value.as_str()).collect();
+        assert_eq!(tokens, [" ", "(", ")"]);
+        let inner = paren.nodes().next().unwrap();
+        assert_eq!(inner.to_string(), "a + b");
+        let kinds: Vec<_> = inner.nodes().map(|x| x.kind).collect();
+        assert_eq!(kinds, [SyntaxKind::Identifier, SyntaxKind::Identifier]);
+    }
+
+    #[test]
+    fn keeps_what_no_rule_matches() {
+        let root = parse("a ) b", "test");
+        let kinds: Vec<_> = root.nodes().map(|x| x.kind).collect();
+        assert_eq!(kinds, [SyntaxKind::Identifier, SyntaxKind::Error]);
+        assert_eq!(root.nodes().nth(1).unwrap().to_string(), " ) b");
+        let e = lower(&root).unwrap_err();
+        assert_eq!(
+            e.message,
+            "unexpected `)`: error at Locator { column: 2, row: 0 }."
+        );
+    }
+
+    #[test]
+    fn lowers_in_a_pass_of_its_own() {
+        let root = parse("/// twice\nx => x + x", "test");
+        let Expr::Function(x) = lower(&root).unwrap() else {
+            panic!()
+        };
+        assert_eq!(x.doc.as_deref(), Some("twice"));
+        assert_eq!(
+            format!("{:?}", x.body),
+            r#"Add { operator: "+", lhs: "x", rhs: "x" }"#
+        );
+        let program = lower_program(&parse("/// one\nlet x = 1; x", "test")).unwrap();
+        assert_eq!(program.to_string(), "/// one\nlet x = 1\nx\n");
+        let e = lower(&parse("let x = 1", "test")).unwrap_err();
+        assert!(e
+            .message
+            .starts_with("expected an expression, not a definition"));
+        let e = lower(&parse("a +", "test")).unwrap_err();
+        assert!(e.message.ends_with("column: 3, row: 0 }."), "{}", e.message);
+    }
+
+    #[test]
+    fn an_indented_block_groups_like_parentheses() {
+        let root = parse("x =>\n  x + 1", "test");
+        let function = root.nodes().next().unwrap();
+        let kinds: Vec<_> = function.nodes().map(|x| x.kind).collect();
+        assert_eq!(kinds, [SyntaxKind::Parameters, SyntaxKind::Block]);
+        assert!(lower(&root).is_ok());
+        let e = lower(&parse("x =>\n  x\n  1", "test")).unwrap_err();
+        assert!(e
+            .message
+            .starts_with("expected a definition, only the last item of a block is its result"));
+        assert!(e.message.ends_with("column: 2, row: 1 }."), "{}", e.message);
+    }
+
+    #[test]
+    fn finds_the_functions_from_the_end() {
+        let tokens: Vec<_> = TouchMeTokenBundle::new("f (x, (y)) => (a, 1) => b", "test")
+            .with_trivia()
+            .collect();
+        let table = functions(&tokens);
+        let starts: Vec<_> = tokens
+            .iter()
+            .zip(table)
+            .filter(|(x, _)| !x.is_trivia())
+            .map(|(x, function)| (x.value.as_str(), function))
+            .collect();
+        let expected = [
+            ("f", true),
+            ("(", true),
+            ("x", false),
+            (",", false),
+            ("(", false),
+            ("y", false),
+            (")", false),
+            (")", false),
+            ("=>", true),
+            ("(", false),
+            ("a", false),
+            (",", false),
+            ("1", false),
+            (")", false),
+            ("=>", true),
+            ("b", false),
+        ];
+        assert_eq!(starts[..expected.len()], expected);
+    }
+}
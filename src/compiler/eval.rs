@@ -0,0 +1,646 @@
+use std::{collections::HashMap, rc::Rc};
+
+use super::ast::{BinaryExpr, Expr, Item, LetItem, MatchExpr, Program, TemplatePart};
+
+/// result of evaluating an `Expr`
+#[derive(Clone, Debug)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Boolean(bool),
+    Tuple(Vec<Value>),
+    Function(Rc<Closure>),
+}
+
+impl Value {
+    /// kind of the value with its article, e.g. `an integer`
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "an integer",
+            Value::Float(_) => "a float",
+            Value::String(_) => "a string",
+            Value::Boolean(_) => "a boolean",
+            Value::Tuple(_) => "a tuple",
+            Value::Function(_) => "a function",
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(x) => write!(f, "{}", x),
+            Value::Float(x) => write!(f, "{:?}", x),
+            Value::String(x) => f.write_str(x),
+            Value::Boolean(x) => write!(f, "{}", x),
+            Value::Tuple(xs) => {
+                f.write_str("(")?;
+                for (i, x) in xs.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", x)?;
+                }
+                f.write_str(")")
+            }
+            Value::Function(x) => write!(f, "<function/{}>", x.params.len() - x.args.len()),
+        }
+    }
+}
+
+/// arrow function with the scope it was made in, and the arguments it has
+/// been partially applied to
+#[derive(Clone, Debug)]
+pub struct Closure {
+    /// name of a recursive definition, in scope in the body
+    pub name: Option<String>,
+    pub params: Vec<Expr>,
+    pub body: Expr,
+    pub env: Env,
+    pub args: Vec<Value>,
+}
+
+/// names in scope and their values
+#[derive(Clone, Debug, Default)]
+pub struct Env {
+    bindings: HashMap<String, Value>,
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.bindings.get(name)
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, value: Value) {
+        self.bindings.insert(name.into(), value);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvalError {
+    pub message: String,
+}
+
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl EvalError {
+    pub fn new(message: &str) -> EvalError {
+        EvalError {
+            message: message.to_string(),
+        }
+    }
+}
+
+fn fail<X>(message: &str) -> Result<X, EvalError> {
+    Err(EvalError::new(message))
+}
+
+/// value of `expr` in `env`
+///
+/// e.g. `(x => x + 1) 2` is `3`, `(x y => x * y) 2` is a function of `y`
+pub fn eval(expr: &Expr, env: &Env) -> Result<Value, EvalError> {
+    match expr {
+        Expr::Integer(x) => Ok(Value::Integer(*x)),
+        Expr::BigInteger(x) => fail(&format!("integer {} is out of range", x)),
+        Expr::Float(x) => Ok(Value::Float(x.0)),
+        Expr::String(x) => Ok(Value::String(x.clone())),
+        Expr::Boolean(x) => Ok(Value::Boolean(*x)),
+        Expr::Identifier(name) => match env.get(name) {
+            Some(x) => Ok(x.clone()),
+            None => fail(&format!("`{}` is not defined", name)),
+        },
+        Expr::Interpolation(x) => {
+            let mut text = String::new();
+            for part in &x.parts {
+                match part {
+                    TemplatePart::Text(x) => text.push_str(x),
+                    TemplatePart::Expr(x) => text.push_str(&eval(x, env)?.to_string()),
+                }
+            }
+            Ok(Value::String(text))
+        }
+        Expr::Comma(x) => Ok(Value::Tuple(
+            x.items
+                .iter()
+                .map(|x| eval(x, env))
+                .collect::<Result<_, _>>()?,
+        )),
+        Expr::Paren(x) => eval(&x.expr, env),
+        Expr::Function(x) => Ok(Value::Function(Rc::new(Closure {
+            name: None,
+            params: x.params.clone(),
+            body: x.body.clone(),
+            env: env.clone(),
+            args: Vec::new(),
+        }))),
+        Expr::FunctionCall(x) => {
+            let callee = eval(&x.callee, env)?;
+            let args = x
+                .args
+                .iter()
+                .map(|x| eval(x, env))
+                .collect::<Result<_, _>>()?;
+            apply(callee, args)
+        }
+        Expr::If(x) => match eval(&x.condition, env)? {
+            Value::Boolean(true) => eval(&x.consequent, env),
+            Value::Boolean(false) => eval(&x.alternative, env),
+            x => fail(&format!(
+                "the condition is {}, not a boolean",
+                x.type_name()
+            )),
+        },
+        Expr::Match(x) => evaluate_match(x, env),
+        Expr::Block(x) => {
+            let mut env = env.clone();
+            for item in &x.items {
+                define(item, &mut env)?;
+            }
+            eval(&x.result, &env)
+        }
+        Expr::And(x) => match eval(&x.lhs, env)? {
+            Value::Boolean(false) => Ok(Value::Boolean(false)),
+            Value::Boolean(true) => boolean(&x.operator, eval(&x.rhs, env)?),
+            lhs => boolean(&x.operator, lhs),
+        },
+        Expr::Or(x) => match eval(&x.lhs, env)? {
+            Value::Boolean(true) => Ok(Value::Boolean(true)),
+            Value::Boolean(false) => boolean(&x.operator, eval(&x.rhs, env)?),
+            lhs => boolean(&x.operator, lhs),
+        },
+        Expr::Add(x)
+        | Expr::Sub(x)
+        | Expr::Mul(x)
+        | Expr::Div(x)
+        | Expr::Rem(x)
+        | Expr::Pow(x)
+        | Expr::Eq(x)
+        | Expr::Ne(x)
+        | Expr::Lt(x)
+        | Expr::Le(x)
+        | Expr::Gt(x)
+        | Expr::Ge(x)
+        | Expr::BitAnd(x)
+        | Expr::BitOr(x)
+        | Expr::BitXor(x)
+        | Expr::Shl(x)
+        | Expr::Shr(x) => binary(x, env),
+        Expr::Neg(x) | Expr::Not(x) | Expr::BitNot(x) => {
+            match (x.operator.as_str(), eval(&x.expr, env)?) {
+                ("-", Value::Integer(x)) => x
+                    .checked_neg()
+                    .map(Value::Integer)
+                    .ok_or_else(|| EvalError::new("integer overflow")),
+                ("-", Value::Float(x)) => Ok(Value::Float(-x)),
+                ("!", Value::Boolean(x)) => Ok(Value::Boolean(!x)),
+                ("~", Value::Integer(x)) => Ok(Value::Integer(!x)),
+                (operator, x) => fail(&format!("cannot apply `{}` to {}", operator, x.type_name())),
+            }
+        }
+    }
+}
+
+/// call `callee` with `args`, fewer arguments than parameters make a
+/// partial application and more ones are passed on to the result
+pub fn apply(callee: Value, args: Vec<Value>) -> Result<Value, EvalError> {
+    let closure = match callee {
+        Value::Function(x) => x,
+        x => return fail(&format!("{} is not callable", x.type_name())),
+    };
+    let mut bound = closure.args.clone();
+    bound.extend(args);
+    if bound.len() < closure.params.len() {
+        return Ok(Value::Function(Rc::new(Closure {
+            args: bound,
+            ..Closure::clone(&closure)
+        })));
+    }
+    let rest = bound.split_off(closure.params.len());
+    let mut env = closure.env.clone();
+    if let Some(name) = &closure.name {
+        let itself = Closure {
+            args: Vec::new(),
+            ..Closure::clone(&closure)
+        };
+        env.insert(name, Value::Function(Rc::new(itself)));
+    }
+    for (param, value) in closure.params.iter().zip(bound) {
+        bind(param, value, &mut env)?;
+    }
+    let result = eval(&closure.body, &env)?;
+    match rest.is_empty() {
+        true => Ok(result),
+        false => apply(result, rest),
+    }
+}
+
+/// evaluate the items of `program` in order, definitions go into `env`,
+/// the module scope, and the result is the value of the last item if it is
+/// an expression
+pub fn run(program: &Program, env: &mut Env) -> Result<Option<Value>, EvalError> {
+    let mut last = None;
+    for item in &program.items {
+        last = match item {
+            Item::Let(x) => {
+                define(x, env)?;
+                None
+            }
+            Item::Expr(x) => Some(eval(x, env)?),
+        };
+    }
+    Ok(last)
+}
+
+/// bind the name of `item` to its value in `env`, a recursive function is
+/// in scope in its own body
+fn define(item: &LetItem, env: &mut Env) -> Result<(), EvalError> {
+    let value = match eval(&item.value, env)? {
+        Value::Function(f) if item.recursive => Value::Function(Rc::new(Closure {
+            name: Some(item.name.clone()),
+            ..Closure::clone(&f)
+        })),
+        value => value,
+    };
+    env.insert(&item.name, value);
+    Ok(())
+}
+
+/// bind the names of the parameter `param` to `value`
+fn bind(param: &Expr, value: Value, env: &mut Env) -> Result<(), EvalError> {
+    match param {
+        Expr::Identifier(name) => {
+            env.insert(name, value);
+            Ok(())
+        }
+        Expr::Comma(x) => match value {
+            Value::Tuple(values) if values.len() == x.items.len() => {
+                for (param, value) in x.items.iter().zip(values) {
+                    bind(param, value, env)?;
+                }
+                Ok(())
+            }
+            value => fail(&format!("cannot bind {} to `({})`", value, param)),
+        },
+        x => fail(&format!("`{}` is not a parameter", x)),
+    }
+}
+
+/// operand of `&&` or `||`, which must be a boolean
+fn boolean(operator: &str, x: Value) -> Result<Value, EvalError> {
+    match x {
+        Value::Boolean(x) => Ok(Value::Boolean(x)),
+        x => fail(&format!("cannot apply `{}` to {}", operator, x.type_name())),
+    }
+}
+
+/// value of the body of the first arm which matches, the parser has
+/// checked the arms against the type their patterns tell, see
+/// `MatchExpr::is_exhaustive`, but not against the value
+fn evaluate_match(x: &MatchExpr, env: &Env) -> Result<Value, EvalError> {
+    let value = eval(&x.scrutinee, env)?;
+    for arm in &x.arms {
+        let mut scope = env.clone();
+        if !matches(&arm.pattern, &value, &mut scope)? {
+            continue;
+        }
+        if let Some(guard) = &arm.guard {
+            match eval(guard, &scope)? {
+                Value::Boolean(true) => {}
+                Value::Boolean(false) => continue,
+                x => return fail(&format!("the guard is {}, not a boolean", x.type_name())),
+            }
+        }
+        return eval(&arm.body, &scope);
+    }
+    fail(&format!("no arm matches {}", value))
+}
+
+/// whether `value` matches `pattern`, binding the names in it into `env`
+fn matches(pattern: &Expr, value: &Value, env: &mut Env) -> Result<bool, EvalError> {
+    match (pattern, value) {
+        (Expr::Identifier(name), _) if name == "_" => Ok(true),
+        (Expr::Identifier(name), value) => {
+            env.insert(name, value.clone());
+            Ok(true)
+        }
+        (Expr::Paren(x), value) => matches(&x.expr, value, env),
+        (Expr::Comma(x), Value::Tuple(values)) if x.items.len() == values.len() => {
+            for (pattern, value) in x.items.iter().zip(values) {
+                if !matches(pattern, value, env)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        (Expr::Comma(_), _) => Ok(false),
+        (_, Value::Function(_)) => Ok(false),
+        (
+            Expr::Integer(_)
+            | Expr::BigInteger(_)
+            | Expr::Float(_)
+            | Expr::String(_)
+            | Expr::Boolean(_),
+            value,
+        ) => equals(&eval(pattern, &Env::new())?, value),
+        (x, _) => fail(&format!("`{}` is not a pattern", x)),
+    }
+}
+
+/// whether two values are equal, functions cannot be compared
+fn equals(lhs: &Value, rhs: &Value) -> Result<bool, EvalError> {
+    match (lhs, rhs) {
+        (Value::Integer(a), Value::Integer(b)) => Ok(a == b),
+        (Value::Integer(a), Value::Float(b)) | (Value::Float(b), Value::Integer(a)) => {
+            Ok(*a as f64 == *b)
+        }
+        (Value::Float(a), Value::Float(b)) => Ok(a == b),
+        (Value::String(a), Value::String(b)) => Ok(a == b),
+        (Value::Boolean(a), Value::Boolean(b)) => Ok(a == b),
+        (Value::Tuple(a), Value::Tuple(b)) if a.len() == b.len() => {
+            for (a, b) in a.iter().zip(b) {
+                if !equals(a, b)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        (Value::Function(_), _) | (_, Value::Function(_)) => fail("functions cannot be compared"),
+        _ => Ok(false),
+    }
+}
+
+fn binary(x: &BinaryExpr, env: &Env) -> Result<Value, EvalError> {
+    let operator = x.operator.as_str();
+    let (lhs, rhs) = (eval(&x.lhs, env)?, eval(&x.rhs, env)?);
+    match (operator, lhs, rhs) {
+        ("==", a, b) => Ok(Value::Boolean(equals(&a, &b)?)),
+        ("!=", a, b) => Ok(Value::Boolean(!equals(&a, &b)?)),
+        ("+", Value::String(a), Value::String(b)) => Ok(Value::String(a + &b)),
+        ("<" | "<=" | ">" | ">=", Value::String(a), Value::String(b)) => {
+            Ok(Value::Boolean(compare(operator, a.cmp(&b))))
+        }
+        ("&" | "|" | "^", Value::Boolean(a), Value::Boolean(b)) => {
+            Ok(Value::Boolean(match operator {
+                "&" => a & b,
+                "|" => a | b,
+                _ => a ^ b,
+            }))
+        }
+        (_, Value::Integer(a), Value::Integer(b)) => integer(operator, a, b),
+        (_, Value::Integer(a), Value::Float(b)) => float(operator, a as f64, b),
+        (_, Value::Float(a), Value::Integer(b)) => float(operator, a, b as f64),
+        (_, Value::Float(a), Value::Float(b)) => float(operator, a, b),
+        (_, a, b) => fail(&format!(
+            "cannot apply `{}` to {} and {}",
+            operator,
+            a.type_name(),
+            b.type_name()
+        )),
+    }
+}
+
+fn compare(operator: &str, ordering: std::cmp::Ordering) -> bool {
+    match operator {
+        "<" => ordering.is_lt(),
+        "<=" => ordering.is_le(),
+        ">" => ordering.is_gt(),
+        _ => ordering.is_ge(),
+    }
+}
+
+fn integer(operator: &str, a: i64, b: i64) -> Result<Value, EvalError> {
+    let x = match operator {
+        "+" => a.checked_add(b),
+        "-" => a.checked_sub(b),
+        "*" => a.checked_mul(b),
+        "/" | "%" if b == 0 => return fail("division by zero"),
+        "/" => a.checked_div(b),
+        "%" => a.checked_rem(b),
+        "**" if b < 0 => return float(operator, a as f64, b as f64),
+        "**" => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+        "&" => Some(a & b),
+        "|" => Some(a | b),
+        "^" => Some(a ^ b),
+        "<<" => u32::try_from(b).ok().and_then(|b| a.checked_shl(b)),
+        ">>" => u32::try_from(b).ok().and_then(|b| a.checked_shr(b)),
+        "<" | "<=" | ">" | ">=" => return Ok(Value::Boolean(compare(operator, a.cmp(&b)))),
+        _ => return fail(&format!("cannot apply `{}` to integers", operator)),
+    };
+    x.map(Value::Integer)
+        .ok_or_else(|| EvalError::new("integer overflow"))
+}
+
+fn float(operator: &str, a: f64, b: f64) -> Result<Value, EvalError> {
+    let x = match operator {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" => a / b,
+        "%" => a % b,
+        "**" => a.powf(b),
+        "<" | "<=" | ">" | ">=" => match a.partial_cmp(&b) {
+            Some(ordering) => return Ok(Value::Boolean(compare(operator, ordering))),
+            None => return Ok(Value::Boolean(false)),
+        },
+        _ => return fail(&format!("cannot apply `{}` to floats", operator)),
+    };
+    Ok(Value::Float(x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::accelerator::parse_program;
+
+    /// value of the last item of `source`, or the error of its run
+    fn value(source: &str) -> String {
+        let program = match parse_program(source) {
+            Ok(x) => x,
+            Err(e) => return e.message,
+        };
+        match run(&program, &mut Env::new()) {
+            Ok(Some(x)) => x.to_string(),
+            Ok(None) => "none".to_string(),
+            Err(e) => e.message,
+        }
+    }
+
+    #[test]
+    fn applies_operators() {
+        assert_eq!(value("1 + 2 * 3 - 4 / 2 % 3"), "5");
+        assert_eq!(value("2 ** 10"), "1024");
+        assert_eq!(value("2 ** -1"), "0.5");
+        assert_eq!(value("7.5 / 2"), "3.75");
+        assert_eq!(value("(6 & 3) + (6 | 3) * 10 + (6 ^ 3) * 100"), "572");
+        assert_eq!(value("(1 << 4) + (-16 >> 2) + ~0"), "11");
+        assert_eq!(value("1 < 2"), "true");
+        assert_eq!(value("'a' < 'b' && 1 == 1.0"), "true");
+        assert_eq!(value("!(1 != 1)"), "true");
+        assert_eq!(value("'to' + 'do'"), "todo");
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        assert_eq!(value("1 > 2 && undefined"), "false");
+        assert_eq!(value("1 < 2 || undefined"), "true");
+        assert_eq!(value("1 < 2 && 1"), "cannot apply `&&` to an integer");
+        assert_eq!(value("1 || 1 < 2"), "cannot apply `||` to an integer");
+    }
+
+    #[test]
+    fn reports_arithmetic_errors() {
+        assert_eq!(value("1 / 0"), "division by zero");
+        assert_eq!(value("9223372036854775807 + 1"), "integer overflow");
+        assert_eq!(value("-'a'"), "cannot apply `-` to a string");
+        assert_eq!(
+            value("1 + 'a'"),
+            "cannot apply `+` to an integer and a string"
+        );
+        assert_eq!(value("1.5 & 1"), "cannot apply `&` to floats");
+        assert_eq!(
+            value("99999999999999999999"),
+            "integer 99999999999999999999 is out of range"
+        );
+    }
+
+    #[test]
+    fn calls_functions() {
+        assert_eq!(value("(add => add 1 2 + add(1, 2)) (x y => x + y)"), "6");
+        assert_eq!(value("(add => add 1) (x y => x + y)"), "<function/1>");
+        assert_eq!(value("(k => k(1, 2)) (x => y => x)"), "1");
+        assert_eq!(value("1 2"), "an integer is not callable");
+        assert_eq!(value("f 1"), "`f` is not defined");
+    }
+
+    #[test]
+    fn calls_what_expressions_return() {
+        assert_eq!(value("(x => x + 1) 2"), "3");
+        assert_eq!(value("(x y => x * y) 2"), "<function/1>");
+        assert_eq!(value("(x y => x * y) 2 3"), "6");
+        assert_eq!(value("(f x => f (f x)) (x => x * 3) 2"), "18");
+        assert_eq!(
+            value("(f g => x => f (g x)) (x => x + 1) (x => x * 2) 5"),
+            "11"
+        );
+    }
+
+    #[test]
+    fn interpolates_values() {
+        assert_eq!(
+            value("(a => \"total: ${a + 1}, ${a * 2.5}\") 1"),
+            "total: 2, 2.5"
+        );
+        assert_eq!(
+            value("(name => 「こんにちは、${name}」) 'x'"),
+            "こんにちは、x"
+        );
+        assert_eq!(value("\"${b}\""), "`b` is not defined");
+    }
+
+    #[test]
+    fn builds_and_binds_tuples() {
+        assert_eq!(value("1, 'a', (1 < 2, 2.5)"), "(1, a, (true, 2.5))");
+        assert_eq!(value("((x, y) => (y, x)) (1, 2)"), "(2, 1)");
+        assert_eq!(value("(((a, b), c) => a + b + c) ((1, 2), 3)"), "6");
+        assert_eq!(
+            value("((x, y) => x) (1, 2, 3)"),
+            "cannot bind (1, 2, 3) to `(x, y)`"
+        );
+        assert_eq!(value("((x, y) => x) 1"), "cannot bind 1 to `(x, y)`");
+    }
+
+    #[test]
+    fn blocks_have_a_scope_of_their_own() {
+        assert_eq!(value("def f x =\n  let y = x\n  y + 1\nf 2"), "3");
+        assert_eq!(
+            value("let y = 1\nlet x =\n  let y = 2\n  y\nx, y"),
+            "(2, 1)"
+        );
+        assert_eq!(value("(let z = 1; z)\nz"), "`z` is not defined");
+        assert_eq!(
+            value("def add x =\n  let y = x\n  z => y + z\nadd 1 2"),
+            "3"
+        );
+    }
+
+    #[test]
+    fn runs_programs_in_one_scope() {
+        assert_eq!(value("let x = 1\nlet y = x + 1\nlet x = 10\nx + y"), "12");
+        assert_eq!(value("def f n = n, f\nf 1"), "(1, <function/1>)");
+        assert_eq!(value("let rec f = n => f\nf 1 2 3"), "<function/1>");
+        assert_eq!(
+            value("def fact n = if n == 0 then 1 else n * fact (n - 1)\nfact 10"),
+            "3628800"
+        );
+        assert_eq!(value("let f = n => f n\nf 1"), "`f` is not defined");
+        assert_eq!(value("1; let x = 2"), "none");
+        let mut env = Env::new();
+        run(&parse_program("let x = 1").unwrap(), &mut env).unwrap();
+        let x = run(&parse_program("x + 1").unwrap(), &mut env).unwrap();
+        assert_eq!(x.map(|x| x.to_string()).as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn conditionals_take_one_branch() {
+        assert_eq!(value("if 1 < 2 then \"yes\" else \"no\""), "yes");
+        assert_eq!(value("if false then 1 / 0 else 2"), "2");
+        let e = value("if 1 then 2 else 3");
+        assert_eq!(e, "the condition is an integer, not a boolean");
+    }
+
+    #[test]
+    fn the_first_matching_arm_wins() {
+        let f = "def f x = match x with 0 => \"zero\" | (a, 0) => a | -1 => 2 | n => n + 1";
+        assert_eq!(value(&format!("{}\nf 0", f)), "zero");
+        assert_eq!(value(&format!("{}\nf (5, 0)", f)), "5");
+        assert_eq!(value(&format!("{}\nf 2", f)), "3");
+        assert_eq!(value(&format!("{}\nf (-1)", f)), "2");
+        let source = "match (1, (2, 3)) with (_, (b, c)) => b + c";
+        assert_eq!(value(source), "5");
+        assert_eq!(
+            value("match \"a\" with \"b\" => 1 | \"a\" => 2 | _ => 3"),
+            "2"
+        );
+        let source = "match 1 with 1 => 1 == 1 || false | _ => false";
+        assert_eq!(value(source), "true");
+    }
+
+    #[test]
+    fn guards_skip_arms() {
+        let f = "def sign n = match n with x if x < 0 => -1 | 0 => 0 | _ => 1";
+        assert_eq!(
+            value(&format!("{}\nsign (-4), sign 0, sign 9", f)),
+            "(-1, 0, 1)"
+        );
+        let e = value("match 1 with x if x => 1 | _ => 2");
+        assert_eq!(e, "the guard is an integer, not a boolean");
+    }
+
+    #[test]
+    fn matches_must_be_exhaustive() {
+        assert_eq!(value("match true with true => 1 | false => 2"), "1");
+        assert_eq!(
+            value("match (true, 1) with (false, _) => 1 | (true, n) => n"),
+            "1"
+        );
+        // checked before the run, whatever the value of the scrutinee
+        let e = value("match true with true => 1");
+        assert!(e.starts_with("expected an arm of `_`"), "{}", e);
+        let e = value("if false then (match 3 with 1 => 1 | 2 => 2) else 0");
+        assert!(e.starts_with("expected an arm of `_`"), "{}", e);
+        // patterns of booleans tell the type, which the value may not have
+        let e = value("match 3 with true => 1 | false => 2");
+        assert_eq!(e, "no arm matches 3");
+    }
+}
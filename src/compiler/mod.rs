@@ -1,7 +1,16 @@
-
 pub mod parser;
+#[cfg(feature = "std")]
 pub mod tokenizer;
+#[cfg(feature = "std")]
 pub mod ast;
+#[cfg(feature = "std")]
+pub mod bigint;
 
+#[cfg(feature = "std")]
 pub mod accelerator;
+#[cfg(feature = "std")]
 pub mod tex;
+#[cfg(feature = "std")]
+pub mod cst;
+#[cfg(feature = "std")]
+pub mod eval;
@@ -1,14 +1,17 @@
+use alloc::string::String;
+
 use super::parsec;
 
-pub fn space(input: &str) -> Result<(&str, String), parsec::ParseError> {
-    parsec::character(' ')(input)
+/// any whitespace, including tabs, newlines and U+3000
+pub fn space(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
+    parsec::token(char::is_whitespace)(input)
 }
 
-pub fn space_asterisk(input: &str) -> Result<(&str, String), parsec::ParseError> {
+pub fn space_asterisk(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
     parsec::asterisk(space)(input)
 }
 
-pub fn space_plus(input: &str) -> Result<(&str, String), parsec::ParseError> {
+pub fn space_plus(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
     parsec::plus(space)(input)
 }
 
@@ -28,3 +31,31 @@ pub fn leak<X>(
     parsec::between(space_plus, space_plus, parser)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn space_is_any_whitespace() {
+        assert_eq!(space_plus(" \t\n\u{3000}x"), Ok(("x", " \t\n\u{3000}".to_string())));
+        assert_eq!(space_asterisk("x"), Ok(("x", "".to_string())));
+        assert!(space_plus("x").is_err());
+    }
+
+    #[test]
+    fn soft_passes_over_spaces_on_both_sides() {
+        assert_eq!(soft(parsec::character('x'))("\n x \ny"), Ok(("y", "x".to_string())));
+        assert!(soft(parsec::character('x'))("# a\nx").is_err());
+    }
+
+    #[test]
+    fn leak_needs_spaces_on_both_sides() {
+        let x = leak(parsec::character('x'));
+        assert_eq!(x("\tx\ny"), Ok(("y", "x".to_string())));
+        assert!(x("x y").is_err());
+        assert!(x(" x").is_err());
+    }
+}
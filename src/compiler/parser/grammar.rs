@@ -0,0 +1,643 @@
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use super::{
+    parsec::{self, character, either, either3, either4, optional, string, token, ParseError},
+    parseco::{err_at, state, Locator, Parser, ParserError, State},
+};
+
+/// PEG expression, loaded at runtime by `Grammar::load`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    /// `'abc'` or `"abc"`
+    Literal(String),
+    /// `[a-z_]` or `[^0-9]`
+    Class(Vec<(char, char)>, bool),
+    /// `.`
+    Any,
+    /// unresolved rule name, only present while loading
+    Reference(String),
+    /// index of a rule in `Grammar::rules`
+    Rule(usize),
+    Sequence(Vec<Expression>),
+    /// ordered choice, `a / b` or `a | b`
+    Choice(Vec<Expression>),
+    /// `e?`
+    Optional(Box<Expression>),
+    /// `e*`
+    Asterisk(Box<Expression>),
+    /// `e+`
+    Plus(Box<Expression>),
+    /// `&e`
+    And(Box<Expression>),
+    /// `!e`
+    Not(Box<Expression>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Definition {
+    pub name: String,
+    pub expression: Expression,
+}
+
+/// generic parse tree, one node per rule application
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tree {
+    pub rule: String,
+    /// source text matched by the rule
+    pub text: String,
+    /// trees of the rules applied inside this one
+    pub children: Vec<Tree>,
+    pub locator: Locator,
+}
+
+/// interpreted PEG grammar, the first definition is the start rule
+///
+/// e.g.
+/// ```text
+/// # comments start with `#`
+/// Add        <- Primary ('+' Primary)*
+/// Primary    <- Integer / Identifier
+/// Integer    <- [0-9]+
+/// Identifier <- [a-zA-Z_] [a-zA-Z0-9_]*
+/// ```
+///
+/// definitions may also be written in EBNF style, `name = a | b ;`.
+/// left recursive rules, as `A <- A 'x' / 'x'`, are rejected by `load`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grammar {
+    pub rules: Vec<Definition>,
+}
+
+impl Grammar {
+    pub fn load(source: &str) -> Result<Grammar, ParserError> {
+        let (residue, _) = spacing(source).map_err(|e| locate(source, e))?;
+        let (residue, rules) = parsec::some(definition)(residue).map_err(|e| locate(source, e))?;
+        if !residue.is_empty() {
+            return Err(locate(source, ParseError::new(residue, "definition")));
+        }
+
+        let mut grammar = Grammar { rules };
+        let names: Vec<String> = grammar.rules.iter().map(|x| x.name.clone()).collect();
+        for definition in grammar.rules.iter_mut() {
+            resolve(&mut definition.expression, &names)?;
+        }
+        check_left_recursion(&grammar.rules)?;
+        Ok(grammar)
+    }
+
+    /// read and load a grammar file
+    #[cfg(feature = "std")]
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Grammar, ParserError> {
+        match std::fs::read_to_string(path.as_ref()) {
+            Ok(source) => Grammar::load(&source),
+            Err(e) => Err(ParserError::new(&format!(
+                "#Grammar cannot read {}: {}",
+                path.as_ref().display(),
+                e
+            ))),
+        }
+    }
+
+    pub fn rule(&self, name: &str) -> Option<RuleParser<'_>> {
+        self.rules
+            .iter()
+            .position(|x| x.name == name)
+            .map(|index| RuleParser {
+                grammar: self,
+                index,
+            })
+    }
+
+    pub fn start(&self) -> RuleParser<'_> {
+        RuleParser {
+            grammar: self,
+            index: 0,
+        }
+    }
+
+    /// parse the whole `input` with the start rule
+    pub fn parse(&self, input: &str) -> Result<Tree, ParserError> {
+        let mut state = state(input);
+        let tree = self.start().parse(&mut state)?;
+        match state.source.as_str().is_empty() {
+            true => Ok(tree),
+            false => err_at("#Grammar unexpected trailing input", state.locator),
+        }
+    }
+
+    fn apply(&self, index: usize, state: &mut State<'_>) -> Result<Tree, ParserError> {
+        let start = state.clone();
+        let definition = &self.rules[index];
+        let mut children = Vec::new();
+        if self
+            .matches(&definition.expression, state, &mut children)
+            .is_err()
+        {
+            *state = start;
+            return err_at(
+                &format!("#Grammar expected {}", definition.name),
+                state.locator,
+            );
+        }
+
+        let source = start.source.as_str();
+        let consumed = source.len() - state.source.as_str().len();
+        Ok(Tree {
+            rule: definition.name.clone(),
+            text: source[..consumed].to_string(),
+            children,
+            locator: start.locator,
+        })
+    }
+
+    fn matches(
+        &self,
+        expression: &Expression,
+        state: &mut State<'_>,
+        children: &mut Vec<Tree>,
+    ) -> Result<(), ParserError> {
+        let locator = state.locator;
+        match expression {
+            Expression::Literal(expected) => {
+                for x in expected.chars() {
+                    if state.next() != Some(x) {
+                        return err_at(&format!("#Grammar expected {:?}", expected), locator);
+                    }
+                }
+                Ok(())
+            }
+            Expression::Class(ranges, negated) => match state.next() {
+                Some(x) if ranges.iter().any(|&(lo, hi)| lo <= x && x <= hi) != *negated => Ok(()),
+                _ => err_at("#Grammar character class", locator),
+            },
+            Expression::Any => match state.next() {
+                Some(_) => Ok(()),
+                None => err_at("#Grammar unexpected end of input", locator),
+            },
+            Expression::Reference(name) => {
+                err_at(&format!("#Grammar unresolved {}", name), locator)
+            }
+            Expression::Rule(index) => {
+                children.push(self.apply(*index, state)?);
+                Ok(())
+            }
+            Expression::Sequence(xs) => {
+                xs.iter().try_for_each(|x| self.matches(x, state, children))
+            }
+            Expression::Choice(xs) => {
+                for x in xs {
+                    if self.attempt(x, state, children).is_some() {
+                        return Ok(());
+                    }
+                }
+                err_at("#Grammar no alternative", locator)
+            }
+            Expression::Optional(x) => {
+                self.attempt(x, state, children);
+                Ok(())
+            }
+            Expression::Asterisk(x) => {
+                while let Some(true) = self.attempt(x, state, children) {}
+                Ok(())
+            }
+            Expression::Plus(x) => {
+                self.matches(x, state, children)?;
+                while let Some(true) = self.attempt(x, state, children) {}
+                Ok(())
+            }
+            Expression::And(x) => self.matches(x, &mut state.clone(), &mut Vec::new()),
+            Expression::Not(x) => match self.matches(x, &mut state.clone(), &mut Vec::new()) {
+                Ok(_) => err_at("#Grammar negative lookahead", locator),
+                Err(_) => Ok(()),
+            },
+        }
+    }
+
+    /// match `expression` on a copy of `state` and commit it on success,
+    /// returns whether any input has been consumed
+    fn attempt(
+        &self,
+        expression: &Expression,
+        state: &mut State<'_>,
+        children: &mut Vec<Tree>,
+    ) -> Option<bool> {
+        let mut attempt = state.clone();
+        let mut found = Vec::new();
+        self.matches(expression, &mut attempt, &mut found).ok()?;
+        let progressed = attempt.index != state.index;
+        *state = attempt;
+        children.append(&mut found);
+        Some(progressed)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RuleParser<'g> {
+    grammar: &'g Grammar,
+    index: usize,
+}
+
+impl<'a> Parser<State<'a>> for RuleParser<'_> {
+    type Value = Tree;
+
+    fn parse(&self, state: &mut State<'a>) -> Result<Self::Value, ParserError> {
+        self.grammar.apply(self.index, state)
+    }
+}
+
+/// the start rule, so that an `Arc<Grammar>` can be shared between threads
+impl<'a> Parser<State<'a>> for Grammar {
+    type Value = Tree;
+
+    fn parse(&self, state: &mut State<'a>) -> Result<Self::Value, ParserError> {
+        self.apply(0, state)
+    }
+}
+
+fn resolve(expression: &mut Expression, names: &[String]) -> Result<(), ParserError> {
+    match expression {
+        Expression::Reference(name) => match names.iter().position(|x| x == name) {
+            Some(index) => {
+                *expression = Expression::Rule(index);
+                Ok(())
+            }
+            None => Err(ParserError::new(&format!(
+                "#Grammar undefined rule {}",
+                name
+            ))),
+        },
+        Expression::Sequence(xs) | Expression::Choice(xs) => {
+            xs.iter_mut().try_for_each(|x| resolve(x, names))
+        }
+        Expression::Optional(x)
+        | Expression::Asterisk(x)
+        | Expression::Plus(x)
+        | Expression::And(x)
+        | Expression::Not(x) => resolve(x, names),
+        _ => Ok(()),
+    }
+}
+
+/// whether `expression` can match without consuming input, given the
+/// nullability of each rule
+fn nullable(expression: &Expression, rules: &[bool]) -> bool {
+    match expression {
+        Expression::Literal(x) => x.is_empty(),
+        Expression::Class(..) | Expression::Any | Expression::Reference(_) => false,
+        Expression::Rule(index) => rules[*index],
+        Expression::Sequence(xs) => xs.iter().all(|x| nullable(x, rules)),
+        Expression::Choice(xs) => xs.iter().any(|x| nullable(x, rules)),
+        Expression::Plus(x) => nullable(x, rules),
+        Expression::Optional(_)
+        | Expression::Asterisk(_)
+        | Expression::And(_)
+        | Expression::Not(_) => true,
+    }
+}
+
+/// rules which `expression` may apply before consuming any input
+fn leftmost(expression: &Expression, rules: &[bool], found: &mut Vec<usize>) {
+    match expression {
+        Expression::Rule(index) => found.push(*index),
+        Expression::Sequence(xs) => {
+            for x in xs {
+                leftmost(x, rules, found);
+                if !nullable(x, rules) {
+                    break;
+                }
+            }
+        }
+        Expression::Choice(xs) => xs.iter().for_each(|x| leftmost(x, rules, found)),
+        Expression::Optional(x)
+        | Expression::Asterisk(x)
+        | Expression::Plus(x)
+        | Expression::And(x)
+        | Expression::Not(x) => leftmost(x, rules, found),
+        _ => {}
+    }
+}
+
+/// reject rules which may apply themselves again at the same position,
+/// directly or through other rules, as they would recurse forever
+fn check_left_recursion(definitions: &[Definition]) -> Result<(), ParserError> {
+    let mut rules = alloc::vec![false; definitions.len()];
+    loop {
+        let next: Vec<bool> = definitions
+            .iter()
+            .map(|x| nullable(&x.expression, &rules))
+            .collect();
+        if next == rules {
+            break;
+        }
+        rules = next;
+    }
+
+    let calls: Vec<Vec<usize>> = definitions
+        .iter()
+        .map(|x| {
+            let mut found = Vec::new();
+            leftmost(&x.expression, &rules, &mut found);
+            found
+        })
+        .collect();
+    for start in 0..definitions.len() {
+        // depth-first search from `start`, keeping the path to report it
+        let mut visited = alloc::vec![false; definitions.len()];
+        let mut path = alloc::vec![(start, 0)];
+        while let Some((rule, next)) = path.last_mut() {
+            let Some(&callee) = calls[*rule].get(*next) else {
+                path.pop();
+                continue;
+            };
+            *next += 1;
+            if callee == start {
+                let names: Vec<&str> = path
+                    .iter()
+                    .map(|&(x, _)| definitions[x].name.as_str())
+                    .chain([definitions[start].name.as_str()])
+                    .collect();
+                return Err(ParserError::new(&format!(
+                    "#Grammar left recursive rule {}",
+                    names.join(" -> ")
+                )));
+            }
+            if !visited[callee] {
+                visited[callee] = true;
+                path.push((callee, 0));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn locate(source: &str, error: ParseError) -> ParserError {
+    let mut state = state(&source[..source.len() - error.location.len()]);
+    while state.next().is_some() {}
+    ParserError::new(&format!(
+        "#Grammar expected {}: error at {:?}.",
+        error.expected, state.locator
+    ))
+}
+
+/// whitespace and `#` comments
+fn spacing(input: &str) -> Result<(&str, ()), ParseError<'_>> {
+    let mut residue = input.trim_start();
+    while let Some(comment) = residue.strip_prefix('#') {
+        residue = comment
+            .find('\n')
+            .map_or("", |i| &comment[i..])
+            .trim_start();
+    }
+    Ok((residue, ()))
+}
+
+fn lexeme<X>(
+    parser: impl Fn(&str) -> Result<(&str, X), ParseError>,
+) -> impl Fn(&str) -> Result<(&str, X), ParseError<'_>> {
+    parsec::skip(parser, spacing)
+}
+
+fn name(input: &str) -> Result<(&str, String), ParseError<'_>> {
+    let head = token(|x| x.is_ascii_alphabetic() || x == '_');
+    let body = parsec::asterisk(token(|x| x.is_ascii_alphanumeric() || x == '_'));
+    parsec::append(head, body)(input)
+}
+
+/// match `<-`, `::=` or `=`
+fn arrow(input: &str) -> Result<(&str, String), ParseError<'_>> {
+    either3(string("<-"), string("::="), string("="))(input)
+}
+
+fn definition(input: &str) -> Result<(&str, Definition), ParseError<'_>> {
+    let head = parsec::skip(lexeme(name), lexeme(arrow));
+    let body = parsec::skip(choice, optional(lexeme(character(';'))));
+    parsec::map(parsec::follow(head, body), |(name, expression)| {
+        Definition { name, expression }
+    })(input)
+}
+
+fn choice(input: &str) -> Result<(&str, Expression), ParseError<'_>> {
+    let slash = lexeme(either(character('/'), character('|')));
+    let parser = parsec::follow(sequence, parsec::many(parsec::drop(slash, sequence)));
+    parsec::map(parser, |(x, mut xs)| match xs.is_empty() {
+        true => x,
+        false => {
+            xs.insert(0, x);
+            Expression::Choice(xs)
+        }
+    })(input)
+}
+
+fn sequence(input: &str) -> Result<(&str, Expression), ParseError<'_>> {
+    parsec::map(parsec::many(prefix), |mut xs| match xs.len() {
+        1 => xs.remove(0),
+        _ => Expression::Sequence(xs),
+    })(input)
+}
+
+fn prefix(input: &str) -> Result<(&str, Expression), ParseError<'_>> {
+    let lookahead = optional(lexeme(either(character('&'), character('!'))));
+    parsec::map(parsec::follow(lookahead, suffix), |(x, e)| {
+        match x.as_deref() {
+            Some("&") => Expression::And(Box::new(e)),
+            Some(_) => Expression::Not(Box::new(e)),
+            None => e,
+        }
+    })(input)
+}
+
+fn suffix(input: &str) -> Result<(&str, Expression), ParseError<'_>> {
+    let quantifier = optional(lexeme(token(|x| x == '?' || x == '*' || x == '+')));
+    parsec::map(parsec::follow(primary, quantifier), |(e, x)| {
+        match x.as_deref() {
+            Some("?") => Expression::Optional(Box::new(e)),
+            Some("*") => Expression::Asterisk(Box::new(e)),
+            Some(_) => Expression::Plus(Box::new(e)),
+            None => e,
+        }
+    })(input)
+}
+
+fn primary(input: &str) -> Result<(&str, Expression), ParseError<'_>> {
+    let group = parsec::between(lexeme(character('(')), lexeme(character(')')), choice);
+    let any = parsec::map(lexeme(character('.')), |_| Expression::Any);
+    either4(
+        reference,
+        group,
+        lexeme(literal),
+        either(lexeme(class), any),
+    )(input)
+}
+
+/// rule name which does not start a new definition
+fn reference(input: &str) -> Result<(&str, Expression), ParseError<'_>> {
+    let (residue, x) = lexeme(name)(input)?;
+    match arrow(residue) {
+        Ok(_) => Err(ParseError::new(input, "reference")),
+        Err(_) => Ok((residue, Expression::Reference(x))),
+    }
+}
+
+fn escaped(input: &str) -> Result<(&str, char), ParseError<'_>> {
+    let mut chars = input.chars();
+    match chars.next() {
+        Some('\\') => match chars.next() {
+            Some('n') => Ok((chars.as_str(), '\n')),
+            Some('r') => Ok((chars.as_str(), '\r')),
+            Some('t') => Ok((chars.as_str(), '\t')),
+            Some(x) => Ok((chars.as_str(), x)),
+            None => Err(ParseError::new(input, "escape")),
+        },
+        Some(x) => Ok((chars.as_str(), x)),
+        None => Err(ParseError::new(input, "character")),
+    }
+}
+
+fn literal(input: &str) -> Result<(&str, Expression), ParseError<'_>> {
+    let delimiter = match input.chars().next() {
+        Some(x) if x == '\'' || x == '"' => x,
+        _ => return Err(ParseError::new(input, "literal")),
+    };
+
+    let mut value = String::new();
+    let mut residue = &input[1..];
+    loop {
+        if let Some(residue) = residue.strip_prefix(delimiter) {
+            return Ok((residue, Expression::Literal(value)));
+        }
+        let (next, x) = escaped(residue)?;
+        value.push(x);
+        residue = next;
+    }
+}
+
+fn class(input: &str) -> Result<(&str, Expression), ParseError<'_>> {
+    let mut residue = match input.strip_prefix('[') {
+        Some(x) => x,
+        None => return Err(ParseError::new(input, "class")),
+    };
+    let negated = match residue.strip_prefix('^') {
+        Some(x) => {
+            residue = x;
+            true
+        }
+        None => false,
+    };
+
+    let mut ranges = Vec::new();
+    loop {
+        if let Some(residue) = residue.strip_prefix(']') {
+            return Ok((residue, Expression::Class(ranges, negated)));
+        }
+        let (next, lo) = escaped(residue)?;
+        residue = next;
+        match residue.strip_prefix('-') {
+            Some(next) if !next.starts_with(']') => {
+                let (next, hi) = escaped(next)?;
+                residue = next;
+                ranges.push((lo, hi));
+            }
+            _ => ranges.push((lo, lo)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARITHMETIC: &str = "
+        # sums of integers and names
+        Add        <- Primary ('+' Primary)*
+        Primary    <- Integer / Identifier
+        Integer    <- [0-9]+
+        Identifier <- [a-zA-Z_] [a-zA-Z0-9_]*
+    ";
+
+    #[test]
+    fn parses_with_the_start_rule() {
+        let grammar = Grammar::load(ARITHMETIC).unwrap();
+        let tree = grammar.parse("x+12").unwrap();
+        assert_eq!(tree.rule, "Add");
+        assert_eq!(tree.text, "x+12");
+        let children: Vec<(&str, &str)> = tree
+            .children
+            .iter()
+            .map(|x| (x.rule.as_str(), x.text.as_str()))
+            .collect();
+        assert_eq!(children, [("Primary", "x"), ("Primary", "12")]);
+        assert_eq!(tree.children[1].locator, Locator::new(2, 0));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn shares_a_grammar_between_threads() {
+        let grammar = alloc::sync::Arc::new(Grammar::load(ARITHMETIC).unwrap());
+        let worker = {
+            let grammar = grammar.clone();
+            std::thread::spawn(move || grammar.parse(&mut state("a+1")).map(|x| x.text))
+        };
+        assert_eq!(worker.join().unwrap(), Ok("a+1".to_string()));
+    }
+
+    #[test]
+    fn rejects_trailing_input() {
+        let grammar = Grammar::load(ARITHMETIC).unwrap();
+        assert!(grammar.parse("x+").is_err());
+        assert!(grammar.parse("1 + 2").is_err());
+    }
+
+    #[test]
+    fn loads_ebnf_style_definitions() {
+        let grammar = Grammar::load("list = item (',' item)* ; item = 'a' | 'b' ;").unwrap();
+        assert_eq!(grammar.rules.len(), 2);
+        assert!(grammar.parse("a,b,a").is_ok());
+        assert!(grammar.parse("a,c").is_err());
+    }
+
+    #[test]
+    fn loads_classes_lookaheads_and_escapes() {
+        let grammar = Grammar::load(r"S <- !'-' [^\n]+ &. / '\n'").unwrap();
+        assert!(grammar.parse("\n").is_ok());
+        assert!(grammar.parse("-a").is_err());
+        assert!(grammar.rule("S").is_some());
+        assert!(grammar.rule("T").is_none());
+    }
+
+    #[test]
+    fn rejects_undefined_rules() {
+        let error = Grammar::load("A <- B").unwrap_err();
+        assert!(error.message.contains("undefined rule B"));
+    }
+
+    #[test]
+    fn rejects_malformed_definitions() {
+        assert!(Grammar::load("A <- 'x").is_err());
+        assert!(Grammar::load("A <- [a-").is_err());
+        assert!(Grammar::load("<- 'x'").is_err());
+    }
+
+    #[test]
+    fn rejects_direct_left_recursion() {
+        let error = Grammar::load("A <- A 'x' / 'x'").unwrap_err();
+        assert!(error.message.contains("left recursive rule A -> A"));
+    }
+
+    #[test]
+    fn rejects_indirect_left_recursion() {
+        let error = Grammar::load("A <- B 'x' / 'x'\nB <- 'y'? A").unwrap_err();
+        assert!(error.message.contains("left recursive rule A -> B -> A"));
+        assert!(Grammar::load("A <- C* B\nB <- A\nC <- 'c'").is_err());
+    }
+
+    #[test]
+    fn accepts_recursion_after_input() {
+        let grammar = Grammar::load("A <- 'x' A / 'x'\nB <- '(' B ')' / ''").unwrap();
+        assert!(grammar.parse("xxx").is_ok());
+    }
+}
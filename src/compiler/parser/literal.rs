@@ -0,0 +1,150 @@
+use alloc::string::String;
+
+use super::casefold::FOLDING;
+
+/// how a literal is compared with the input
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Case {
+    #[default]
+    Sensitive,
+    /// `A-Z` match `a-z`
+    AsciiInsensitive,
+    /// compare the unicode case foldings, e.g. `Straße` matches `STRASSE`
+    Insensitive,
+}
+
+/// full case folding of `x`, the C and F entries of CaseFolding.txt
+pub fn fold(x: char) -> impl Iterator<Item = char> {
+    let folded = FOLDING
+        .binary_search_by_key(&x, |&(y, _)| y)
+        .ok()
+        .map(|i| FOLDING[i].1);
+    let unchanged = folded.is_none().then_some(x);
+    folded.unwrap_or_default().chars().chain(unchanged)
+}
+
+/// literal compared with the input, folded once for `Case::Insensitive`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Literal<S> {
+    expected: S,
+    case: Case,
+    /// case folding of `expected`, empty unless `Case::Insensitive`
+    folded: String,
+}
+
+impl<S: AsRef<str>> Literal<S> {
+    pub fn new(expected: S, case: Case) -> Self {
+        let folded = match case {
+            Case::Insensitive => expected.as_ref().chars().flat_map(fold).collect(),
+            _ => String::new(),
+        };
+        Literal {
+            expected,
+            case,
+            folded,
+        }
+    }
+
+    pub fn expected(&self) -> &str {
+        self.expected.as_ref()
+    }
+
+    /// byte length of the prefix of `input` matching the literal
+    pub fn prefix(&self, input: &str) -> Option<usize> {
+        let expected = self.expected.as_ref();
+        match self.case {
+            Case::Sensitive => input.starts_with(expected).then_some(expected.len()),
+            Case::AsciiInsensitive => {
+                let mut chars = input.char_indices();
+                for y in expected.chars() {
+                    match chars.next() {
+                        Some((_, x)) if x.eq_ignore_ascii_case(&y) => {}
+                        _ => return None,
+                    }
+                }
+                Some(chars.next().map_or(input.len(), |(i, _)| i))
+            }
+            Case::Insensitive => {
+                // a character of the input folds to one or more characters,
+                // all of which must be matched, `ß` does not match `s`
+                let mut folded = self.folded.chars().peekable();
+                let mut end = 0;
+                for (i, x) in input.char_indices() {
+                    if folded.peek().is_none() {
+                        break;
+                    }
+                    if !fold(x).all(|y| folded.next() == Some(y)) {
+                        return None;
+                    }
+                    end = i + x.len_utf8();
+                }
+                folded.peek().is_none().then_some(end)
+            }
+        }
+    }
+}
+
+/// byte length of the prefix of `input` matching `expected`
+pub fn prefix(input: &str, expected: &str, case: Case) -> Option<usize> {
+    Literal::new(expected, case).prefix(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folded(x: char) -> String {
+        fold(x).collect()
+    }
+
+    #[test]
+    fn folds_simple_and_full_mappings() {
+        assert_eq!(folded('A'), "a");
+        assert_eq!(folded('a'), "a");
+        assert_eq!(folded('ß'), "ss");
+        assert_eq!(folded('ẞ'), "ss");
+        assert_eq!(folded('ς'), "σ");
+        assert_eq!(folded('ﬁ'), "fi");
+        assert_eq!(folded('İ'), "i\u{307}");
+        assert_eq!(folded('Ω'), "ω");
+        assert_eq!(folded('Ꭰ'), "Ꭰ");
+        assert_eq!(folded('ꭰ'), "Ꭰ");
+        assert_eq!(folded('𐐀'), "𐐨");
+        assert_eq!(folded('あ'), "あ");
+    }
+
+    #[test]
+    fn table_is_sorted() {
+        assert!(FOLDING.windows(2).all(|x| x[0].0 < x[1].0));
+    }
+
+    #[test]
+    fn matches_with_the_case_sensitivity() {
+        assert_eq!(prefix("letx", "let", Case::Sensitive), Some(3));
+        assert_eq!(prefix("LET", "let", Case::Sensitive), None);
+        assert_eq!(prefix("LETx", "let", Case::AsciiInsensitive), Some(3));
+        assert_eq!(prefix("Lé", "lÉ", Case::AsciiInsensitive), None);
+        assert_eq!(prefix("Lé", "lÉ", Case::Insensitive), Some(3));
+        assert_eq!(prefix("le", "let", Case::AsciiInsensitive), None);
+    }
+
+    #[test]
+    fn matches_full_foldings() {
+        assert_eq!(prefix("STRASSE!", "Straße", Case::Insensitive), Some(7));
+        assert_eq!(prefix("straße", "STRASSE", Case::Insensitive), Some(7));
+        assert_eq!(prefix("ﬁle", "FILE", Case::Insensitive), Some(5));
+        assert_eq!(prefix("ΣΑΣ", "σας", Case::Insensitive), Some(6));
+        // `ß` folds to `ss` as a whole
+        assert_eq!(prefix("ß", "s", Case::Insensitive), None);
+        assert_eq!(prefix("STR", "Straße", Case::Insensitive), None);
+        assert_eq!(prefix("x", "", Case::Insensitive), Some(0));
+    }
+
+    #[test]
+    fn folds_the_literal_once() {
+        let literal = Literal::new("Straße", Case::Insensitive);
+        assert_eq!(literal.folded, "strasse");
+        assert_eq!(literal.expected(), "Straße");
+        assert_eq!(Literal::new("Straße", Case::Sensitive).folded, "");
+    }
+}
@@ -2,3 +2,7 @@
 pub mod parsec;
 pub mod parseco;
 pub mod combinators;
+pub mod grammar;
+pub mod regex;
+pub mod literal;
+mod casefold;
@@ -1,3 +1,17 @@
+use alloc::{
+    borrow::ToOwned,
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+
+use super::{
+    literal::{Case, Literal},
+    regex::Regex,
+};
+
 #[derive(Debug, PartialEq)]
 pub struct ParseError<'a> {
     pub location: &'a str,
@@ -7,12 +21,42 @@ pub struct ParseError<'a> {
 impl ParseError<'_> {
     pub fn new<'a>(location: &'a str, expected: &'a str) -> ParseError<'a> {
         ParseError {
-            location: location,
+            location,
             expected: expected.to_string(),
         }
     }
 }
 
+/// type erased parser, e.g. to store it in a collection
+pub type BoxedParser<'p, X> =
+    Box<dyn for<'a> Fn(&'a str) -> Result<(&'a str, X), ParseError<'a>> + Send + Sync + 'p>;
+
+/// type erased parser shared between owners or threads,
+/// pass it to other combinators as `&*parser`
+pub type SharedParser<'p, X> =
+    Arc<dyn for<'a> Fn(&'a str) -> Result<(&'a str, X), ParseError<'a>> + Send + Sync + 'p>;
+
+pub fn boxed<'p, X>(
+    parser: impl Fn(&str) -> Result<(&str, X), ParseError> + Send + Sync + 'p,
+) -> BoxedParser<'p, X> {
+    Box::new(parser)
+}
+
+pub fn shared<'p, X>(
+    parser: impl Fn(&str) -> Result<(&str, X), ParseError> + Send + Sync + 'p,
+) -> SharedParser<'p, X> {
+    Arc::new(parser)
+}
+
+impl core::fmt::Display for ParseError<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "expected {} at {:?}", self.expected, self.location)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError<'_> {}
+
 pub fn map<F, X, Y>(
     parser: impl Fn(&str) -> Result<(&str, X), ParseError>,
     morph: F,
@@ -81,6 +125,18 @@ pub fn either4<A>(
     either(either3(a, b, c), d)
 }
 
+/// first successful parser of a runtime collection
+pub fn choice<A>(
+    parsers: Vec<impl Fn(&str) -> Result<(&str, A), ParseError>>,
+) -> impl Fn(&str) -> Result<(&str, A), ParseError> {
+    move |input| {
+        parsers
+            .iter()
+            .find_map(|parser| parser(input).ok())
+            .ok_or_else(|| ParseError::new(input, "choice of parsers"))
+    }
+}
+
 pub fn skip<A, B>(
     prev: impl Fn(&str) -> Result<(&str, A), ParseError>,
     succ: impl Fn(&str) -> Result<(&str, B), ParseError>,
@@ -95,6 +151,15 @@ pub fn drop<A, B>(
     map(follow(prev, succ), |x| x.1)
 }
 
+pub fn optional<T>(
+    parser: impl Fn(&str) -> Result<(&str, T), ParseError>,
+) -> impl Fn(&str) -> Result<(&str, Option<T>), ParseError> {
+    move |input| match parser(input) {
+        Ok((residue, x)) => Ok((residue, Some(x))),
+        Err(_) => Ok((input, None)),
+    }
+}
+
 pub fn many<T>(
     parser: impl Fn(&str) -> Result<(&str, T), ParseError>,
 ) -> impl Fn(&str) -> Result<(&str, Vec<T>), ParseError> {
@@ -102,14 +167,10 @@ pub fn many<T>(
         let mut result = Vec::new();
         let mut remaining_input = input;
 
-        if input.len() >= 1 {
-            loop {
-                if let Ok((next_input, parse_result)) = parser(remaining_input) {
-                    result.push(parse_result);
-                    remaining_input = next_input;
-                } else {
-                    break;
-                }
+        if !input.is_empty() {
+            while let Ok((next_input, parse_result)) = parser(remaining_input) {
+                result.push(parse_result);
+                remaining_input = next_input;
             }
         }
 
@@ -121,7 +182,7 @@ pub fn some<T>(
     parser: impl Fn(&str) -> Result<(&str, T), ParseError>,
 ) -> impl Fn(&str) -> Result<(&str, Vec<T>), ParseError> {
     move |input| match many(|x| parser(x))(input) {
-        Ok(x) if x.1.len() >= 1 => Ok(x),
+        Ok(x) if !x.1.is_empty() => Ok(x),
         _ => Err(ParseError::new(input, "some length should ge 1")),
     }
 }
@@ -168,17 +229,24 @@ pub fn plus(
 //     }
 // }
 
-pub fn string<'a>(
-    expected: &'static str,
-) -> impl Fn(&'a str) -> Result<(&'a str, String), ParseError<'a>> {
-    move |input| match input.starts_with(expected) {
-        true => {
-            let len = expected.len();
-            Ok((&input[len..], input[..len].to_owned()))
-        }
-        false => Err(ParseError {
+/// match `expected`, which may be owned or borrowed
+pub fn string<S: AsRef<str>>(expected: S) -> impl Fn(&str) -> Result<(&str, String), ParseError> {
+    string_with(expected, Case::Sensitive)
+}
+
+/// match `expected` with the given case sensitivity, yields the input text
+///
+/// e.g. `string_with("select", Case::AsciiInsensitive)` matches `SELECT`
+pub fn string_with<S: AsRef<str>>(
+    expected: S,
+    case: Case,
+) -> impl Fn(&str) -> Result<(&str, String), ParseError> {
+    let literal = Literal::new(expected, case);
+    move |input| match literal.prefix(input) {
+        Some(len) => Ok((&input[len..], input[..len].to_owned())),
+        None => Err(ParseError {
             location: input,
-            expected: expected.to_string(),
+            expected: literal.expected().to_string(),
         }),
     }
 }
@@ -207,7 +275,7 @@ pub fn tokens<F>(len: usize, predicate: F) -> impl Fn(&str) -> Result<(&str, Str
 where
     F: Fn(&str) -> bool,
 {
-    move |input| match len <= input.len() {
+    move |input| match len <= input.len() && input.is_char_boundary(len) {
         true => {
             let substr = &input[..len];
             match predicate(substr) {
@@ -264,3 +332,118 @@ pub fn between<A, B, X>(
 ) -> impl Fn(&str) -> Result<(&str, X), ParseError> {
     skip(drop(before, parser), after)
 }
+
+/// match the regular expression `pattern` at the start of the input,
+/// panics if `pattern` is invalid (see `Regex::new`)
+///
+/// e.g. `regex("[A-Za-z_$][A-Za-z0-9_$'-]*")`
+pub fn regex(pattern: &str) -> impl Fn(&str) -> Result<(&str, String), ParseError> {
+    let regex = Regex::new(pattern).unwrap_or_else(|e| panic!("{:?}", e));
+    move |input| match regex.captures_at(input).and_then(|x| x[0]) {
+        Some((_, end)) => Ok((&input[end..], input[..end].to_string())),
+        None => Err(ParseError {
+            location: input,
+            expected: format!("match /{}/", regex.as_str()),
+        }),
+    }
+}
+
+/// like `regex` but yields the groups, group `0` is the whole match
+pub fn captures(pattern: &str) -> impl Fn(&str) -> Result<(&str, Vec<Option<String>>), ParseError> {
+    let regex = Regex::new(pattern).unwrap_or_else(|e| panic!("{:?}", e));
+    move |input| match regex.captures_at(input) {
+        Some(groups) => {
+            let end = groups[0].map_or(0, |x| x.1);
+            let groups = groups
+                .iter()
+                .map(|x| x.map(|(start, end)| input[start..end].to_string()))
+                .collect();
+            Ok((&input[end..], groups))
+        }
+        None => Err(ParseError {
+            location: input,
+            expected: format!("match /{}/", regex.as_str()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn error_points_at_the_failure() {
+        let error = character('a')("xyz").unwrap_err();
+        assert_eq!(error.location, "xyz");
+    }
+
+    #[test]
+    fn many_stops_at_the_first_failure() {
+        let digits = many(token(|x| x.is_ascii_digit()));
+        assert_eq!(
+            digits("12a"),
+            Ok(("a", vec!["1".to_string(), "2".to_string()]))
+        );
+        assert_eq!(digits("a"), Ok(("a", vec![])));
+        assert_eq!(digits(""), Ok(("", vec![])));
+    }
+
+    #[test]
+    fn some_needs_one_match() {
+        let digits = some(token(|x| x.is_ascii_digit()));
+        assert_eq!(digits("1a"), Ok(("a", vec!["1".to_string()])));
+        assert!(digits("a").is_err());
+    }
+
+    #[test]
+    fn optional_never_fails() {
+        let sign = optional(character('-'));
+        assert_eq!(sign("-1"), Ok(("1", Some("-".to_string()))));
+        assert_eq!(sign("1"), Ok(("1", None)));
+    }
+
+    #[test]
+    fn regex_matches_at_the_start() {
+        let name = regex("[A-Za-z_$][A-Za-z0-9_$'-]*");
+        assert_eq!(name("a-b' c"), Ok((" c", "a-b'".to_string())));
+        assert_eq!(
+            name("1a").unwrap_err().expected,
+            "match /[A-Za-z_$][A-Za-z0-9_$'-]*/"
+        );
+
+        let pair = captures("(a)(b)?");
+        let groups = vec![Some("a".to_string()), Some("a".to_string()), None];
+        assert_eq!(pair("ac"), Ok(("c", groups)));
+    }
+
+    #[test]
+    fn string_matches_without_case() {
+        let select = string_with("select", Case::AsciiInsensitive);
+        assert_eq!(select("SELECT *"), Ok((" *", "SELECT".to_string())));
+        assert_eq!(select("sel").unwrap_err().expected, "select");
+        let street = string_with("STRASSE", Case::Insensitive);
+        assert_eq!(street("straße!"), Ok(("!", "straße".to_string())));
+    }
+
+    #[test]
+    fn boxed_parsers_share_a_collection() {
+        let letters = vec![boxed(character('l')), boxed(character('d'))];
+        let letter = choice(letters);
+        assert_eq!(letter("def"), Ok(("ef", "d".to_string())));
+        assert!(letter("if").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn shared_parsers_work_from_threads() {
+        let digits = shared(some(token(|x| x.is_ascii_digit())));
+        let worker = {
+            let digits = digits.clone();
+            std::thread::spawn(move || digits("12a").map(|(_, x)| x.len()).ok())
+        };
+        assert_eq!(worker.join().unwrap(), Some(2));
+        assert!(many(&*digits)("1a2").is_ok());
+    }
+}
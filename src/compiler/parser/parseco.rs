@@ -1,6 +1,17 @@
+use alloc::{
+    boxed::Box,
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+
+use super::literal::{Case, Literal};
+
 #[derive(Clone, Debug)]
 pub struct State<'a> {
-    pub(crate) source: std::str::Chars<'a>,
+    pub(crate) source: core::str::Chars<'a>,
     pub(crate) locator: Locator,
     // pub(crate) size: usize,
     pub(crate) index: usize,
@@ -52,8 +63,9 @@ impl<'a> Iterator for State<'a> {
         let x = self.source.next()?;
         self.locator = match x {
             '\n' => Locator::new(0, self.locator.row + 1),
+            // columns start from 0, a tab moves to the next multiple of 8
             '\t' => Locator {
-                column: self.locator.column + 8 - (self.locator.column - 1) % 8,
+                column: (self.locator.column / 8 + 1) * 8,
                 ..self.locator
             },
             _ => self.locator.default(),
@@ -68,8 +80,8 @@ pub struct ParserError {
     pub message: String,
 }
 
-impl std::fmt::Debug for ParserError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for ParserError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         // f.debug_struct("ParserError")
         //     .field("message", &self.message)
         //     .finish()
@@ -77,19 +89,28 @@ impl std::fmt::Debug for ParserError {
     }
 }
 
+impl core::fmt::Display for ParserError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParserError {}
+
 impl ParserError {
-    pub fn new<'a>(message: &'a str) -> ParserError {
+    pub fn new(message: &str) -> ParserError {
         ParserError {
             message: message.to_string(),
         }
     }
 }
 
-pub fn err<'a, X>(message: &'a str) -> Result<X, ParserError> {
+pub fn err<X>(message: &str) -> Result<X, ParserError> {
     Err(ParserError::new(message))
 }
 
-pub fn err_at<'a, X>(message: &'a str, locator: Locator) -> Result<X, ParserError> {
+pub fn err_at<X>(message: &str, locator: Locator) -> Result<X, ParserError> {
     err(format!("{}: error at {:?}.", message, locator).as_str())
 }
 
@@ -141,6 +162,105 @@ pub trait Parser<A> {
     {
         Or::new(self, succ)
     }
+
+    /// parse `self` then `succ`, yields both values
+    fn follow<B>(self, succ: B) -> Follow<Self, B>
+    where
+        Self: Sized,
+        B: Parser<A>,
+    {
+        Follow { prev: self, succ }
+    }
+
+    /// parse `self` then `succ`, yields the value of `self`
+    fn skip<B>(self, succ: B) -> Skip<Self, B>
+    where
+        Self: Sized,
+        B: Parser<A>,
+    {
+        Skip { prev: self, succ }
+    }
+
+    /// parse `self` then `succ`, yields the value of `succ`
+    fn drop<B>(self, succ: B) -> Drop<Self, B>
+    where
+        Self: Sized,
+        B: Parser<A>,
+    {
+        Drop { prev: self, succ }
+    }
+
+    /// never fails, restores the state when `self` fails
+    fn optional(self) -> Optional<Self>
+    where
+        Self: Sized,
+    {
+        Optional { parser: self }
+    }
+
+    /// like `map` but the morphism may reject the value
+    fn and_then<B, F>(self, morph: F) -> AndThen<Self, F>
+    where
+        Self: Sized,
+        F: Fn(Self::Value) -> Result<B, ParserError>,
+    {
+        AndThen {
+            parser: self,
+            morph,
+        }
+    }
+
+    /// erase the type of the parser, e.g. to store it in a collection
+    fn boxed<'p>(self) -> BoxedParser<'p, A, Self::Value>
+    where
+        Self: Sized + Send + Sync + 'p,
+    {
+        Box::new(self)
+    }
+
+    /// erase the type of the parser and share it, e.g. between threads
+    fn shared<'p>(self) -> SharedParser<'p, A, Self::Value>
+    where
+        Self: Sized + Send + Sync + 'p,
+    {
+        Arc::new(self)
+    }
+}
+
+pub type BoxedParser<'p, S, V> = Box<dyn Parser<S, Value = V> + Send + Sync + 'p>;
+
+pub type SharedParser<'p, S, V> = Arc<dyn Parser<S, Value = V> + Send + Sync + 'p>;
+
+impl<S, P: Parser<S> + ?Sized> Parser<S> for &P {
+    type Value = P::Value;
+
+    fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
+        (**self).parse(state)
+    }
+}
+
+impl<S, P: Parser<S> + ?Sized> Parser<S> for Box<P> {
+    type Value = P::Value;
+
+    fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
+        (**self).parse(state)
+    }
+}
+
+impl<S, P: Parser<S> + ?Sized> Parser<S> for Rc<P> {
+    type Value = P::Value;
+
+    fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
+        (**self).parse(state)
+    }
+}
+
+impl<S, P: Parser<S> + ?Sized> Parser<S> for Arc<P> {
+    type Value = P::Value;
+
+    fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
+        (**self).parse(state)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -179,16 +299,21 @@ pub struct Any<A> {
     parser: A,
 }
 
-impl<S, A: Parser<S>> Parser<S> for Any<A> {
+/// parse on a copy of `state`, which is committed on success only
+fn attempt<S: Clone, A: Parser<S>>(parser: &A, state: &mut S) -> Option<A::Value> {
+    let mut state_copied = state.clone();
+    let x = parser.parse(&mut state_copied).ok()?;
+    *state = state_copied;
+    Some(x)
+}
+
+impl<S: Clone, A: Parser<S>> Parser<S> for Any<A> {
     type Value = Vec<A::Value>;
 
     fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
         let mut vec = Vec::new();
-        loop {
-            match self.parser.parse(state) {
-                Ok(x) => vec.push(x),
-                Err(_) => break,
-            }
+        while let Some(x) = attempt(&self.parser, state) {
+            vec.push(x);
         }
         Ok(vec)
     }
@@ -227,7 +352,7 @@ pub struct Asterisk<A> {
     parser: A,
 }
 
-impl<S, A> Parser<S> for Asterisk<A>
+impl<S: Clone, A> Parser<S> for Asterisk<A>
 where
     A: Parser<S, Value = String>,
 {
@@ -235,11 +360,8 @@ where
 
     fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
         let mut buffer = "".to_string();
-        loop {
-            match self.parser.parse(state) {
-                Ok(x) => buffer += &x,
-                Err(_) => break,
-            }
+        while let Some(x) = attempt(&self.parser, state) {
+            buffer += &x;
         }
         Ok(buffer)
     }
@@ -249,7 +371,7 @@ pub struct Plus<A> {
     parser: A,
 }
 
-impl<S, A> Parser<S> for Plus<A>
+impl<S: Clone, A> Parser<S> for Plus<A>
 where
     A: Parser<S, Value = String>,
 {
@@ -258,11 +380,8 @@ where
     fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
         match self.parser.parse(state) {
             Ok(mut s) => {
-                loop {
-                    match self.parser.parse(state) {
-                        Ok(x) => s += &x,
-                        Err(_) => break,
-                    }
+                while let Some(x) = attempt(&self.parser, state) {
+                    s += &x;
                 }
                 Ok(s)
             }
@@ -294,8 +413,136 @@ where
         let mut state_copied = state.clone();
         match self.prev.parse(state) {
             Ok(x) => Ok(x),
-            Err(_) => self.succ.parse(&mut state_copied),
+            Err(_) => {
+                let x = self.succ.parse(&mut state_copied);
+                *state = state_copied;
+                x
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Follow<A, B> {
+    prev: A,
+    succ: B,
+}
+
+impl<S, A: Parser<S>, B: Parser<S>> Parser<S> for Follow<A, B> {
+    type Value = (A::Value, B::Value);
+
+    fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
+        let a = self.prev.parse(state)?;
+        let b = self.succ.parse(state)?;
+        Ok((a, b))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Skip<A, B> {
+    prev: A,
+    succ: B,
+}
+
+impl<S, A: Parser<S>, B: Parser<S>> Parser<S> for Skip<A, B> {
+    type Value = A::Value;
+
+    fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
+        let a = self.prev.parse(state)?;
+        self.succ.parse(state)?;
+        Ok(a)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Drop<A, B> {
+    prev: A,
+    succ: B,
+}
+
+impl<S, A: Parser<S>, B: Parser<S>> Parser<S> for Drop<A, B> {
+    type Value = B::Value;
+
+    fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
+        self.prev.parse(state)?;
+        self.succ.parse(state)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Optional<A> {
+    parser: A,
+}
+
+impl<S: Clone, A: Parser<S>> Parser<S> for Optional<A> {
+    type Value = Option<A::Value>;
+
+    fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
+        Ok(attempt(&self.parser, state))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AndThen<P, F> {
+    parser: P,
+    morph: F,
+}
+
+impl<S, B, P: Parser<S>, F> Parser<S> for AndThen<P, F>
+where
+    F: Fn(P::Value) -> Result<B, ParserError>,
+{
+    type Value = B;
+
+    fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
+        self.parser.parse(state).and_then(&self.morph)
+    }
+}
+
+/// parser defined by a function, e.g. to write recursive grammars
+#[derive(Clone, Copy, Debug)]
+pub struct FromFn<F> {
+    parse: F,
+}
+
+pub fn from_fn<S, V, F>(parse: F) -> FromFn<F>
+where
+    F: Fn(&mut S) -> Result<V, ParserError>,
+{
+    FromFn { parse }
+}
+
+impl<S, V, F> Parser<S> for FromFn<F>
+where
+    F: Fn(&mut S) -> Result<V, ParserError>,
+{
+    type Value = V;
+
+    fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
+        (self.parse)(state)
+    }
+}
+
+/// first successful parser of a runtime collection
+#[derive(Clone, Debug)]
+pub struct Choice<P> {
+    parsers: Vec<P>,
+}
+
+pub fn choice<P>(parsers: Vec<P>) -> Choice<P> {
+    Choice { parsers }
+}
+
+impl<S: Clone, P: Parser<S>> Parser<S> for Choice<P> {
+    type Value = P::Value;
+
+    fn parse(&self, state: &mut S) -> Result<Self::Value, ParserError> {
+        for parser in &self.parsers {
+            if let Some(x) = attempt(parser, state) {
+                return Ok(x);
+            }
         }
+        err("choice but no parser succeeded")
     }
 }
 
@@ -332,11 +579,11 @@ where
     Satisfied::new(predicate)
 }
 
-// remark: closure type unique
+// remark: closure type unique, use `Parser::boxed` to store several of them
 #[macro_export]
 macro_rules! character {
     ($x:literal) => {
-        parseco::piece(|x| *x == $x)
+        $crate::compiler::parser::parseco::piece(|x| *x == $x)
     };
 }
 
@@ -345,19 +592,149 @@ macro_rules! character {
 
 
 
-pub fn pieces<F>(predicate: F) -> Satisfied<F>
-where
-    F: Fn(&str) -> bool,
-{
-    Satisfied::new(predicate)
+#[derive(Clone, Debug)]
+pub struct Pieces<S> {
+    literal: Literal<S>,
 }
 
+impl<'a, S: AsRef<str>> Parser<State<'a>> for Pieces<S> {
+    type Value = String;
 
-// // remark: closure type unique
-// #[macro_export]
-// macro_rules! string {
-//     ($s:literal) => {
-//         parseco::pieces(|x| x.eq($s))
-//     };
-// }
+    fn parse(&self, state: &mut State<'a>) -> Result<Self::Value, ParserError> {
+        let input = state.source.as_str();
+        match self.literal.prefix(input) {
+            Some(len) => {
+                for _ in input[..len].chars() {
+                    state.next();
+                }
+                Ok(input[..len].to_string())
+            }
+            None => err_at(
+                &format!("#Pieces {:?}", self.literal.expected()),
+                state.locator,
+            ),
+        }
+    }
+}
 
+/// match `expected`, which may be owned or borrowed
+pub fn pieces<S: AsRef<str>>(expected: S) -> Pieces<S> {
+    pieces_with(expected, Case::Sensitive)
+}
+
+/// match `expected` with the given case sensitivity, yields the input text
+pub fn pieces_with<S: AsRef<str>>(expected: S, case: Case) -> Pieces<S> {
+    Pieces {
+        literal: Literal::new(expected, case),
+    }
+}
+
+// parsers and their errors can be built once and used from worker threads
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ParserError>();
+    assert_send_sync::<State<'static>>();
+    assert_send_sync::<BoxedParser<'static, State<'static>, String>>();
+    assert_send_sync::<SharedParser<'static, State<'static>, String>>();
+    assert_send_sync::<super::grammar::Grammar>();
+    assert_send_sync::<super::regex::Regex>();
+};
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    fn locator_after(input: &str) -> Locator {
+        let mut state = state(input);
+        while state.next().is_some() {}
+        state.locator
+    }
+
+    #[test]
+    fn error_messages_carry_the_locator() {
+        let error = err_at::<()>("#Test", Locator::new(3, 1)).unwrap_err();
+        assert_eq!(error.message, "#Test: error at Locator { column: 3, row: 1 }.");
+    }
+
+    #[test]
+    fn tab_moves_to_the_next_stop() {
+        assert_eq!(locator_after("\t"), Locator::new(8, 0));
+        assert_eq!(locator_after("abc\t"), Locator::new(8, 0));
+        assert_eq!(locator_after("abcdefgh\t"), Locator::new(16, 0));
+        assert_eq!(locator_after("a\n\t\tb"), Locator::new(17, 1));
+    }
+
+    #[test]
+    fn or_goes_on_from_the_second_parser() {
+        let b = piece(|x| *x == 'b').map(|x| x.to_string());
+        let a_or_bb = piece(|x| *x == 'a').map(|x| x.to_string()).or(b.twice());
+        let mut input = state("bbc");
+        assert_eq!(a_or_bb.parse(&mut input), Ok("bb".to_string()));
+        assert_eq!(input.source.as_str(), "c");
+    }
+
+    #[test]
+    fn repetitions_stop_before_the_first_failure() {
+        let a = piece(|x| *x == 'a').map(|x| x.to_string());
+        let mut input = state("aab");
+        assert_eq!(a.any().parse(&mut input), Ok(vec!["a".to_string(); 2]));
+        assert_eq!(input.source.as_str(), "b");
+        let mut input = state("aab");
+        assert_eq!(a.asterisk().parse(&mut input), Ok("aa".to_string()));
+        assert_eq!(input.source.as_str(), "b");
+        let mut input = state("ab");
+        assert_eq!(a.plus().parse(&mut input), Ok("a".to_string()));
+        assert_eq!(input.source.as_str(), "b");
+        assert!(a.plus().parse(&mut state("b")).is_err());
+    }
+
+    #[test]
+    fn pieces_match_a_prefix() {
+        let mut input = state("abc");
+        assert_eq!(pieces("ab").parse(&mut input), Ok("ab".to_string()));
+        assert_eq!(input.source.as_str(), "c");
+        assert_eq!(input.locator, Locator::new(2, 0));
+
+        let error = pieces("ab").parse(&mut state("ac")).unwrap_err();
+        assert_eq!(error.message, "#Pieces \"ab\": error at Locator { column: 0, row: 0 }.");
+    }
+
+    #[test]
+    fn pieces_match_without_case() {
+        let select = pieces_with("select", Case::AsciiInsensitive);
+        assert_eq!(select.parse(&mut state("SELECT *")), Ok("SELECT".to_string()));
+        let street = pieces_with(String::from("Straße"), Case::Insensitive);
+        assert_eq!(street.parse(&mut state("STRASSE")), Ok("STRASSE".to_string()));
+        assert!(street.parse(&mut state("STRAS")).is_err());
+    }
+
+    #[test]
+    fn boxed_parsers_share_a_collection() {
+        let l = piece(|x| *x == 'l').map(|x| x.to_string());
+        let d = piece(|x| *x == 'd').map(|x| x.to_string());
+        let keywords: Vec<BoxedParser<'_, State<'_>, String>> = vec![l.boxed(), d.boxed()];
+        let keyword = choice(keywords);
+        let mut input = state("def");
+        assert_eq!(keyword.parse(&mut input), Ok("d".to_string()));
+        assert_eq!(input.source.as_str(), "ef");
+        assert!(keyword.parse(&mut state("if")).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn shared_parsers_work_from_threads() {
+        let a = piece(|x| *x == 'a').map(|x| x.to_string());
+        let parser = a.plus().shared();
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let parser = parser.clone();
+                std::thread::spawn(move || parser.parse(&mut state("aab")))
+            })
+            .collect();
+        for worker in workers {
+            assert_eq!(worker.join().unwrap(), Ok("aa".to_string()));
+        }
+    }
+}
@@ -0,0 +1,629 @@
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use super::parseco::{err, err_at, Parser, ParserError, State};
+
+/// compiled regular expression, matched by a pike vm over its nfa
+///
+/// supports
+/// - literals, `.` and escapes `\n \t \r \\ \. ...`
+/// - classes `[a-z_]`, `[^0-9]`, `\d \w \s` and their negations `\D \W \S`
+/// - repetitions `* + ? {n} {n,} {n,m}`, lazy when followed by `?`
+/// - alternations `a|b`, groups `(a)` and non capturing groups `(?:a)`
+/// - anchors `^` and `$`
+///
+/// as a combinator the regex is matched at the current position of the input,
+/// preferring alternatives and repetitions from left to right
+#[derive(Clone, Debug)]
+pub struct Regex {
+    pattern: String,
+    program: Vec<Instruction>,
+    groups: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ClassItem {
+    Range(char, char),
+    Digit,
+    Word,
+    Space,
+    NotDigit,
+    NotWord,
+    NotSpace,
+}
+
+impl ClassItem {
+    fn contains(&self, x: char) -> bool {
+        match self {
+            ClassItem::Range(lo, hi) => *lo <= x && x <= *hi,
+            ClassItem::Digit => x.is_ascii_digit(),
+            ClassItem::Word => x.is_ascii_alphanumeric() || x == '_',
+            ClassItem::Space => x.is_whitespace(),
+            ClassItem::NotDigit => !x.is_ascii_digit(),
+            ClassItem::NotWord => !(x.is_ascii_alphanumeric() || x == '_'),
+            ClassItem::NotSpace => !x.is_whitespace(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Empty,
+    Char(char),
+    Class(Vec<ClassItem>, bool),
+    Any,
+    Start,
+    End,
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Instruction {
+    Char(char),
+    Class(Vec<ClassItem>, bool),
+    Any,
+    Start,
+    End,
+    Save(usize),
+    /// fork, the first branch has priority
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+/// byte ranges of the groups of a match, group `0` is the whole match
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, ParserError> {
+        let mut compiler = Compiler {
+            chars: pattern.chars().collect(),
+            index: 0,
+            groups: 0,
+        };
+        let node = compiler.alternate()?;
+        if compiler.index < compiler.chars.len() {
+            return compiler.error("unmatched `)`");
+        }
+
+        let mut program = vec![Instruction::Save(0)];
+        emit(&node, &mut program);
+        program.push(Instruction::Save(1));
+        program.push(Instruction::Match);
+        Ok(Regex {
+            pattern: pattern.to_string(),
+            program,
+            groups: compiler.groups + 1,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// number of groups including the whole match
+    pub fn groups(&self) -> usize {
+        self.groups
+    }
+
+    /// match at the start of `input`
+    pub fn captures_at(&self, input: &str) -> Option<Captures> {
+        self.run(input, 0)
+    }
+
+    /// leftmost match anywhere in `input`
+    pub fn find(&self, input: &str) -> Option<(usize, usize)> {
+        input
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(Some(input.len()))
+            .find_map(|start| self.run(input, start))
+            .and_then(|captures| captures[0])
+    }
+
+    pub fn is_match(&self, input: &str) -> bool {
+        self.find(input).is_some()
+    }
+
+    /// `Parser` yielding the groups instead of the matched text
+    pub fn capturing(&self) -> Capturing<'_> {
+        Capturing { regex: self }
+    }
+
+    fn run(&self, input: &str, start: usize) -> Option<Captures> {
+        let slots = vec![None; self.groups * 2];
+        let mut current = Vec::new();
+        let mut visited = vec![usize::MAX; self.program.len()];
+        self.follow(&mut current, &mut visited, 0, input, start, slots);
+
+        let mut matched = None;
+        let mut position = start;
+        loop {
+            let x = input[position..].chars().next();
+            let next_position = position + x.map_or(0, char::len_utf8);
+            let mut next = Vec::new();
+            for (pc, slots) in current {
+                let accepted = match (&self.program[pc], x) {
+                    (Instruction::Match, _) => {
+                        matched = Some(slots);
+                        break;
+                    }
+                    (Instruction::Char(c), Some(x)) => *c == x,
+                    (Instruction::Class(items, negated), Some(x)) => {
+                        items.iter().any(|item| item.contains(x)) != *negated
+                    }
+                    (Instruction::Any, Some(_)) => true,
+                    _ => false,
+                };
+                if accepted {
+                    self.follow(&mut next, &mut visited, pc + 1, input, next_position, slots);
+                }
+            }
+            if x.is_none() || next.is_empty() {
+                break;
+            }
+            position = next_position;
+            current = next;
+        }
+
+        matched.map(|slots| {
+            (0..self.groups)
+                .map(|i| match (slots[2 * i], slots[2 * i + 1]) {
+                    (Some(start), Some(end)) => Some((start, end)),
+                    _ => None,
+                })
+                .collect()
+        })
+    }
+
+    /// add the thread at `pc` and everything reachable from it without input
+    fn follow(
+        &self,
+        threads: &mut Vec<(usize, Vec<Option<usize>>)>,
+        visited: &mut [usize],
+        pc: usize,
+        input: &str,
+        position: usize,
+        mut slots: Vec<Option<usize>>,
+    ) {
+        if visited[pc] == position {
+            return;
+        }
+        visited[pc] = position;
+
+        match self.program[pc] {
+            Instruction::Jump(x) => self.follow(threads, visited, x, input, position, slots),
+            Instruction::Split(x, y) => {
+                self.follow(threads, visited, x, input, position, slots.clone());
+                self.follow(threads, visited, y, input, position, slots);
+            }
+            Instruction::Save(slot) => {
+                slots[slot] = Some(position);
+                self.follow(threads, visited, pc + 1, input, position, slots);
+            }
+            Instruction::Start if position == 0 => {
+                self.follow(threads, visited, pc + 1, input, position, slots)
+            }
+            Instruction::End if position == input.len() => {
+                self.follow(threads, visited, pc + 1, input, position, slots)
+            }
+            Instruction::Start | Instruction::End => {}
+            _ => threads.push((pc, slots)),
+        }
+    }
+}
+
+impl<'a> Parser<State<'a>> for Regex {
+    type Value = String;
+
+    fn parse(&self, state: &mut State<'a>) -> Result<Self::Value, ParserError> {
+        let input = state.source.as_str();
+        match self.captures_at(input).and_then(|captures| captures[0]) {
+            Some((_, end)) => {
+                for _ in input[..end].chars() {
+                    state.next();
+                }
+                Ok(input[..end].to_string())
+            }
+            None => err_at(&format!("#Regex {}", self.pattern), state.locator),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Capturing<'r> {
+    regex: &'r Regex,
+}
+
+impl<'a> Parser<State<'a>> for Capturing<'_> {
+    type Value = Vec<Option<String>>;
+
+    fn parse(&self, state: &mut State<'a>) -> Result<Self::Value, ParserError> {
+        let input = state.source.as_str();
+        match self.regex.captures_at(input) {
+            Some(captures) => {
+                let end = captures[0].map_or(0, |x| x.1);
+                for _ in input[..end].chars() {
+                    state.next();
+                }
+                Ok(captures
+                    .iter()
+                    .map(|x| x.map(|(start, end)| input[start..end].to_string()))
+                    .collect())
+            }
+            None => err_at(&format!("#Regex {}", self.regex.pattern), state.locator),
+        }
+    }
+}
+
+struct Compiler {
+    chars: Vec<char>,
+    index: usize,
+    groups: usize,
+}
+
+impl Compiler {
+    fn error<X>(&self, message: &str) -> Result<X, ParserError> {
+        err(&format!("#Regex {} at {}", message, self.index))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn eat(&mut self, x: char) -> bool {
+        let matched = self.peek() == Some(x);
+        if matched {
+            self.index += 1;
+        }
+        matched
+    }
+
+    fn next(&mut self) -> Result<char, ParserError> {
+        match self.peek() {
+            Some(x) => {
+                self.index += 1;
+                Ok(x)
+            }
+            None => self.error("unexpected end of pattern"),
+        }
+    }
+
+    fn alternate(&mut self) -> Result<Node, ParserError> {
+        let mut xs = vec![self.concat()?];
+        while self.eat('|') {
+            xs.push(self.concat()?);
+        }
+        Ok(match xs.len() {
+            1 => xs.remove(0),
+            _ => Node::Alternate(xs),
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node, ParserError> {
+        let mut xs = Vec::new();
+        while let Some(x) = self.peek() {
+            if x == '|' || x == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            xs.push(self.repeat(atom)?);
+        }
+        Ok(match xs.len() {
+            0 => Node::Empty,
+            1 => xs.remove(0),
+            _ => Node::Concat(xs),
+        })
+    }
+
+    fn atom(&mut self) -> Result<Node, ParserError> {
+        match self.next()? {
+            '(' => {
+                let index = match self.eat('?') {
+                    true if self.eat(':') => None,
+                    true => return self.error("unknown group flag"),
+                    false => {
+                        self.groups += 1;
+                        Some(self.groups)
+                    }
+                };
+                let node = self.alternate()?;
+                match self.eat(')') {
+                    true => Ok(Node::Group(Box::new(node), index)),
+                    false => self.error("unclosed group"),
+                }
+            }
+            '[' => self.class(),
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '\\' => match self.escape()? {
+                ClassItem::Range(x, _) => Ok(Node::Char(x)),
+                item => Ok(Node::Class(vec![item], false)),
+            },
+            '*' | '+' | '?' | '{' => self.error("nothing to repeat"),
+            x => Ok(Node::Char(x)),
+        }
+    }
+
+    fn escape(&mut self) -> Result<ClassItem, ParserError> {
+        let x = match self.next()? {
+            'd' => return Ok(ClassItem::Digit),
+            'w' => return Ok(ClassItem::Word),
+            's' => return Ok(ClassItem::Space),
+            'D' => return Ok(ClassItem::NotDigit),
+            'W' => return Ok(ClassItem::NotWord),
+            'S' => return Ok(ClassItem::NotSpace),
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            x if x.is_ascii_alphanumeric() => return self.error("unknown escape"),
+            x => x,
+        };
+        Ok(ClassItem::Range(x, x))
+    }
+
+    fn class(&mut self) -> Result<Node, ParserError> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        loop {
+            let lo = match self.next()? {
+                ']' if !items.is_empty() => return Ok(Node::Class(items, negated)),
+                '\\' => match self.escape()? {
+                    ClassItem::Range(x, _) => x,
+                    item => {
+                        items.push(item);
+                        continue;
+                    }
+                },
+                x => x,
+            };
+            if self.peek() == Some('-') && self.chars.get(self.index + 1) != Some(&']') {
+                self.index += 1;
+                let hi = match self.next()? {
+                    '\\' => match self.escape()? {
+                        ClassItem::Range(x, _) => x,
+                        _ => return self.error("invalid class range"),
+                    },
+                    x => x,
+                };
+                if hi < lo {
+                    return self.error("invalid class range");
+                }
+                items.push(ClassItem::Range(lo, hi));
+            } else {
+                items.push(ClassItem::Range(lo, lo));
+            }
+        }
+    }
+
+    fn repeat(&mut self, node: Node) -> Result<Node, ParserError> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.index += 1;
+                let min = self.number()?;
+                let max = match self.eat(',') {
+                    true if self.peek() == Some('}') => None,
+                    true => Some(self.number()?),
+                    false => Some(min),
+                };
+                if !self.eat('}') {
+                    return self.error("unclosed repetition");
+                }
+                if max.is_some_and(|max| max < min) {
+                    return self.error("invalid repetition range");
+                }
+                self.index -= 1;
+                (min, max)
+            }
+            _ => return Ok(node),
+        };
+        self.index += 1;
+        let greedy = !self.eat('?');
+        let node = Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+            greedy,
+        };
+        match self.peek() {
+            Some('*') | Some('+') | Some('?') | Some('{') => self.error("nested repetition"),
+            _ => Ok(node),
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, ParserError> {
+        let start = self.index;
+        while self.peek().is_some_and(|x| x.is_ascii_digit()) {
+            self.index += 1;
+        }
+        let digits: String = self.chars[start..self.index].iter().collect();
+        match digits.parse() {
+            Ok(x) => Ok(x),
+            Err(_) => self.error("invalid repetition count"),
+        }
+    }
+}
+
+fn emit(node: &Node, program: &mut Vec<Instruction>) {
+    match node {
+        Node::Empty => {}
+        Node::Char(x) => program.push(Instruction::Char(*x)),
+        Node::Class(items, negated) => program.push(Instruction::Class(items.clone(), *negated)),
+        Node::Any => program.push(Instruction::Any),
+        Node::Start => program.push(Instruction::Start),
+        Node::End => program.push(Instruction::End),
+        Node::Group(node, None) => emit(node, program),
+        Node::Group(node, Some(index)) => {
+            program.push(Instruction::Save(2 * index));
+            emit(node, program);
+            program.push(Instruction::Save(2 * index + 1));
+        }
+        Node::Concat(xs) => xs.iter().for_each(|x| emit(x, program)),
+        Node::Alternate(xs) => {
+            let mut jumps = Vec::new();
+            for (i, x) in xs.iter().enumerate() {
+                if i + 1 < xs.len() {
+                    let split = program.len();
+                    program.push(Instruction::Split(split + 1, 0));
+                    emit(x, program);
+                    jumps.push(program.len());
+                    program.push(Instruction::Jump(0));
+                    program[split] = Instruction::Split(split + 1, program.len());
+                } else {
+                    emit(x, program);
+                }
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Instruction::Jump(end);
+            }
+        }
+        Node::Repeat {
+            node,
+            min,
+            max,
+            greedy,
+        } => {
+            for _ in 0..*min {
+                emit(node, program);
+            }
+            let split = |program: &mut Vec<Instruction>, at: usize, body: usize, out: usize| {
+                program[at] = match greedy {
+                    true => Instruction::Split(body, out),
+                    false => Instruction::Split(out, body),
+                };
+            };
+            match max {
+                None => {
+                    let at = program.len();
+                    program.push(Instruction::Jump(0));
+                    emit(node, program);
+                    program.push(Instruction::Jump(at));
+                    let out = program.len();
+                    split(program, at, at + 1, out);
+                }
+                Some(max) => {
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Instruction::Jump(0));
+                        emit(node, program);
+                    }
+                    let out = program.len();
+                    for at in splits {
+                        split(program, at, at + 1, out);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::compiler::parser::parseco::state;
+
+    fn matched(pattern: &str, input: &str) -> Option<String> {
+        let regex = Regex::new(pattern).unwrap();
+        let captures = regex.captures_at(input)?;
+        captures[0].map(|(start, end)| input[start..end].to_string())
+    }
+
+    #[test]
+    fn matches_at_the_start() {
+        assert_eq!(matched("ab", "abc").as_deref(), Some("ab"));
+        assert_eq!(matched("ab", "cab"), None);
+        assert_eq!(matched("a.c", "a\u{3042}c").as_deref(), Some("a\u{3042}c"));
+        assert_eq!(matched(r"\.\\", r".\x").as_deref(), Some(r".\"));
+    }
+
+    #[test]
+    fn matches_classes() {
+        assert_eq!(matched("[a-z_]+", "ab_c1").as_deref(), Some("ab_c"));
+        assert_eq!(matched("[^0-9]+", "ab1").as_deref(), Some("ab"));
+        assert_eq!(matched(r"\d+\s\w+", "12 ab!").as_deref(), Some("12 ab"));
+        assert_eq!(matched(r"\D\W\S", "a!b").as_deref(), Some("a!b"));
+    }
+
+    #[test]
+    fn repetitions_are_greedy_unless_lazy() {
+        assert_eq!(matched("a*", "aaab").as_deref(), Some("aaa"));
+        assert_eq!(matched("a*?", "aaab").as_deref(), Some(""));
+        assert_eq!(matched("a+?b", "aaab").as_deref(), Some("aaab"));
+        assert_eq!(matched("a{2}", "aaa").as_deref(), Some("aa"));
+        assert_eq!(matched("a{2,}", "aaaa").as_deref(), Some("aaaa"));
+        assert_eq!(matched("a{1,2}", "aaa").as_deref(), Some("aa"));
+        assert_eq!(matched("a{2}", "a"), None);
+        assert_eq!(matched("ab?c", "ac").as_deref(), Some("ac"));
+    }
+
+    #[test]
+    fn alternatives_prefer_the_left() {
+        assert_eq!(matched("a|ab", "ab").as_deref(), Some("a"));
+        assert_eq!(matched("(?:ab|a)c", "ac").as_deref(), Some("ac"));
+    }
+
+    #[test]
+    fn captures_groups() {
+        let regex = Regex::new("(a)(?:b)(c)?").unwrap();
+        assert_eq!(regex.groups(), 3);
+        assert_eq!(
+            regex.captures_at("ab"),
+            Some(vec![Some((0, 2)), Some((0, 1)), None])
+        );
+    }
+
+    #[test]
+    fn anchors_and_find() {
+        let regex = Regex::new("b+$").unwrap();
+        assert_eq!(regex.find("abb"), Some((1, 3)));
+        assert_eq!(regex.find("abba"), None);
+        assert!(Regex::new("^a").unwrap().is_match("ab"));
+        assert!(!Regex::new("^b").unwrap().is_match("ab"));
+    }
+
+    #[test]
+    fn rejects_malformed_patterns() {
+        assert!(Regex::new("(a").is_err());
+        assert!(Regex::new("a)").is_err());
+        assert!(Regex::new("[a-").is_err());
+        assert!(Regex::new("*a").is_err());
+        assert!(Regex::new("a{2,1}").is_err());
+    }
+
+    #[test]
+    fn parses_as_a_combinator() {
+        let regex = Regex::new("[0-9]+").unwrap();
+        let mut input = state("12ab");
+        assert_eq!(regex.parse(&mut input), Ok("12".to_string()));
+        assert_eq!(input.source.as_str(), "ab");
+        assert!(regex.parse(&mut input).is_err());
+
+        let groups = Regex::new("(a)(b)?").unwrap();
+        assert_eq!(
+            groups.capturing().parse(&mut state("ac")),
+            Ok(vec![Some("a".to_string()), Some("a".to_string()), None])
+        );
+    }
+}
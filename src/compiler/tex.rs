@@ -1,15 +1,791 @@
-
-use crate::character;
+use std::collections::{HashMap, VecDeque};
 
 use super::{
-    ast::{BinaryExpr, Expr, FunctionExpr},
     parser::{
-        combinators::{self},
-        parsec::{self, between, character, token, tokens},
+        parsec::{self, token, ParseError},
+        parseco::{self, Locator},
+    },
+    tokenizer::{
+        ascii_alias, block_comment, canonical, closing_delimiter, identifier_warning,
+        identifier_with, is_number_start, is_string_start, keyword_with, line_comment, normalize,
+        number, raw_string_opening, skeleton, template_literal, unalias, Hyphen, OperatorRegistry,
     },
-    tokenizer::{valid_string_content, identifier},
 };
 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TouchMeTokenCategory {
+    EOF,
+    /// source text which no rule matches, the lexer goes on after it
+    Error,
+    /// `// ...` or `/* ... */`, and `/// ...` doc comments
+    Comment,
+    /// run of whitespace, only in the trivia mode
+    Whitespace,
+    String,
+    Number,
+    Identifier,
+    /// reserved word, see `tokenizer::KEYWORDS`
+    Keyword,
+    /// `\name`, or `\` followed by a single character
+    Macro,
+    Operator,
+    /// virtual token of the layout mode, a line indented more than the
+    /// enclosing block opens a block
+    Indent,
+    /// virtual token of the layout mode, closes a block
+    Dedent,
+    /// virtual token of the layout mode, separates the lines of a block
+    Newline,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TouchMeToken {
+    pub category: TouchMeTokenCategory,
+    /// starts from 1
+    pub lineno: u32,
+    /// starts from 1
+    pub columno: u32,
+    /// byte offset in the source
+    pub position: u32,
+    /// byte length in the source
+    pub length: u32,
+    pub filename: String,
+    /// source text of the token
+    pub value: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// message of the lexer about a position in the source
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// starts from 1
+    pub lineno: u32,
+    /// starts from 1
+    pub columno: u32,
+    /// byte offset in the source
+    pub position: u32,
+    pub filename: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.filename, self.lineno, self.columno, severity, self.message
+        )
+    }
+}
+
+impl TouchMeToken {
+    pub fn locator(&self) -> Locator {
+        Locator::new(self.columno as usize - 1, self.lineno as usize - 1)
+    }
+
+    /// whether the token is a comment or whitespace
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self.category,
+            TouchMeTokenCategory::Comment | TouchMeTokenCategory::Whitespace
+        )
+    }
+
+    /// source text, with full-width operators and digits in their ASCII forms
+    pub fn canonical(&self) -> String {
+        match self.category {
+            TouchMeTokenCategory::Operator | TouchMeTokenCategory::Number => canonical(&self.value),
+            _ => self.value.clone(),
+        }
+    }
+}
+
+/// lexer, iterates over the tokens of `buffer` and ends with `EOF`
+#[derive(Clone, Debug)]
+pub struct TouchMeTokenBundle {
+    buffer: String,
+    /// byte offset of `buffer` in the enclosing source
+    base: usize,
+    offset: usize,
+    locator: Locator,
+    filename: String,
+    done: bool,
+    diagnostics: Vec<Diagnostic>,
+    /// whether indentation delimits blocks
+    layout: bool,
+    /// columns of the open blocks, from the outermost one
+    indents: Vec<usize>,
+    /// depth of the open brackets, which suspend the layout
+    depth: usize,
+    /// whether the next token starts a line
+    line_start: bool,
+    /// whether a token other than a comment has been lexed
+    started: bool,
+    /// whether whitespace is lexed as `Whitespace` tokens
+    trivia: bool,
+    /// virtual tokens to yield before the next one of the source
+    pending: VecDeque<TouchMeToken>,
+    /// first identifier of each confusable skeleton
+    skeletons: HashMap<String, String>,
+    hyphen: Hyphen,
+    operators: OperatorRegistry,
+    /// whether to warn when full-width and half-width forms are mixed
+    width_warnings: bool,
+    /// whether the first operator or number is in full-width forms
+    width: Option<bool>,
+}
+
+impl TouchMeTokenBundle {
+    pub fn new(buffer: impl Into<String>, filename: impl Into<String>) -> Self {
+        TouchMeTokenBundle {
+            buffer: buffer.into(),
+            base: 0,
+            offset: 0,
+            locator: Locator::new(0, 0),
+            filename: filename.into(),
+            done: false,
+            diagnostics: Vec::new(),
+            layout: false,
+            indents: vec![0],
+            depth: 0,
+            line_start: true,
+            started: false,
+            trivia: false,
+            pending: VecDeque::new(),
+            skeletons: HashMap::new(),
+            hyphen: Hyphen::default(),
+            operators: OperatorRegistry::default(),
+            width_warnings: false,
+            width: None,
+        }
+    }
+
+    /// lex `buffer` as the text at byte `position` and at `locator` of an
+    /// enclosing source, e.g. the code embedded in a template string
+    pub fn with_start(self, position: usize, locator: Locator) -> Self {
+        TouchMeTokenBundle {
+            base: position,
+            locator,
+            ..self
+        }
+    }
+
+    /// operators to lex by maximal munch
+    pub fn with_operators(self, operators: OperatorRegistry) -> Self {
+        TouchMeTokenBundle { operators, ..self }
+    }
+
+    /// operators which the lexer matches
+    pub fn operators(&self) -> &OperatorRegistry {
+        &self.operators
+    }
+
+    /// warn about operators and numbers in full-width forms in a source
+    /// which has them in half-width forms, or the other way round
+    pub fn with_width_warnings(self) -> Self {
+        TouchMeTokenBundle {
+            width_warnings: true,
+            ..self
+        }
+    }
+
+    /// trivia mode, in which whitespace is lexed as well, so that the tokens
+    /// cover the whole source
+    pub fn with_trivia(self) -> Self {
+        TouchMeTokenBundle {
+            trivia: true,
+            ..self
+        }
+    }
+
+    /// rule for `-` in identifiers
+    pub fn with_hyphen(self, hyphen: Hyphen) -> Self {
+        TouchMeTokenBundle { hyphen, ..self }
+    }
+
+    /// layout mode, in which the lexer inserts `Indent`, `Dedent` and
+    /// `Newline` by the columns of the lines, as the offside rule does
+    pub fn with_layout(self) -> Self {
+        TouchMeTokenBundle {
+            layout: true,
+            ..self
+        }
+    }
+
+    /// diagnostics of the tokens lexed so far
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// report `message` at `length` bytes after the current offset
+    fn diagnose(&mut self, severity: Severity, length: usize, message: String) {
+        let mut state = parseco::state(&self.buffer[self.offset..self.offset + length]);
+        state.locator = self.locator;
+        while state.next().is_some() {}
+        self.diagnostics.push(Diagnostic {
+            severity,
+            lineno: state.locator.row as u32 + 1,
+            columno: state.locator.column as u32 + 1,
+            position: (self.base + self.offset + length) as u32,
+            filename: self.filename.clone(),
+            message,
+        });
+    }
+
+    /// move over `length` bytes, keeping the locator in sync
+    fn advance(&mut self, length: usize) {
+        let mut state = parseco::state(&self.buffer[self.offset..self.offset + length]);
+        state.locator = self.locator;
+        while state.next().is_some() {}
+        self.locator = state.locator;
+        self.offset += length;
+    }
+
+    /// warn about an identifier which may be misread, or which looks like
+    /// another one of the source
+    fn check_identifier(&mut self, value: &str) {
+        let name = normalize(value);
+        if let Some(message) = identifier_warning(&name) {
+            self.diagnose(Severity::Warning, 0, message);
+        }
+        match self.skeletons.get(&skeleton(&name)) {
+            Some(other) if *other != name => {
+                let message = format!("identifier `{}` is confusable with `{}`", name, other);
+                self.diagnose(Severity::Warning, 0, message);
+            }
+            Some(_) => {}
+            None => {
+                self.skeletons.insert(skeleton(&name), name.clone());
+            }
+        }
+
+        // `a-b` reads as a subtraction if `a` and `b` are names themselves,
+        // and `x-1` does whatever the rule for `-` is
+        let parts: Vec<&str> = name.split('-').collect();
+        let seen = |x: &&str| self.skeletons.get(&skeleton(x)).is_some_and(|y| y == x);
+        let numeric = parts[1..]
+            .iter()
+            .any(|x| !x.starts_with(char::is_alphabetic));
+        if parts.len() > 1 && (numeric || parts.iter().all(seen)) {
+            let message = format!(
+                "`{}` is one identifier, write `{}` for a subtraction",
+                name,
+                parts.join(" - ")
+            );
+            self.diagnose(Severity::Warning, 0, message);
+        }
+    }
+
+    /// warn about an operator or a number in another width than the first
+    fn check_width(&mut self, value: &str) {
+        let full = value.chars().any(|x| ascii_alias(x).is_some());
+        if *self.width.get_or_insert(full) != full {
+            let message = match full {
+                true => format!("full-width `{}` among half-width forms", value),
+                false => format!("half-width `{}` among full-width forms", value),
+            };
+            self.diagnose(Severity::Warning, 0, message);
+        }
+    }
+
+    /// warn about a `-` with a space on one side only, as in `a -b`, which
+    /// reads as a prefix minus
+    fn check_minus(&mut self, length: usize) {
+        let before = self.buffer[..self.offset].chars().next_back();
+        let after = self.buffer[self.offset + length..].chars().next();
+        let (Some(before), Some(after)) = (before, after) else {
+            return;
+        };
+        if before.is_whitespace() != after.is_whitespace() && !"([,".contains(unalias(before)) {
+            let message =
+                "`-` has a space on one side only, it may not be the minus meant".to_string();
+            self.diagnose(Severity::Warning, 0, message);
+        }
+    }
+
+    /// virtual tokens for the column of a new line, the first line sets
+    /// the column of the outermost block
+    fn indent(&mut self) {
+        let column = self.locator.column;
+        if !self.started {
+            self.indents = vec![column];
+            return;
+        }
+        if self.indents.last().is_some_and(|&x| column > x) {
+            self.indents.push(column);
+            let token = self.token(TouchMeTokenCategory::Indent, 0);
+            self.pending.push_back(token);
+            return;
+        }
+        self.dedent(column);
+        if self.indents.last() != Some(&column) {
+            let message = "unindent does not match any outer indentation level".to_string();
+            self.diagnose(Severity::Error, 0, message);
+        }
+        if self.started {
+            let token = self.token(TouchMeTokenCategory::Newline, 0);
+            self.pending.push_back(token);
+        }
+    }
+
+    /// close the blocks indented more than `column`, but not the outermost
+    fn dedent(&mut self, column: usize) {
+        while self.indents.len() > 1 && self.indents.last().is_some_and(|&x| x > column) {
+            self.indents.pop();
+            let token = self.token(TouchMeTokenCategory::Dedent, 0);
+            self.pending.push_back(token);
+        }
+    }
+
+    fn token(&self, category: TouchMeTokenCategory, length: usize) -> TouchMeToken {
+        TouchMeToken {
+            category,
+            lineno: self.locator.row as u32 + 1,
+            columno: self.locator.column as u32 + 1,
+            position: (self.base + self.offset) as u32,
+            length: length as u32,
+            filename: self.filename.clone(),
+            value: self.buffer[self.offset..self.offset + length].to_string(),
+        }
+    }
+}
+
+/// category and byte length of the token at the start of `input` with the
+/// given rule for `-` in identifiers and the given operators, with the
+/// error of an `Error` token if a rule has one
+fn classify<'a>(
+    input: &'a str,
+    hyphen: Hyphen,
+    operators: &OperatorRegistry,
+) -> (TouchMeTokenCategory, usize, Option<ParseError<'a>>) {
+    let identifier = identifier_with(hyphen);
+    let keyword = keyword_with(hyphen);
+    let length = |residue: &str| input.len() - residue.len();
+    let macro_name = parsec::drop(
+        parsec::character('\\'),
+        parsec::either(&identifier, token(|x| !x.is_whitespace())),
+    );
+
+    if is_string_start(input) {
+        match template_literal(input) {
+            Ok((residue, _)) => (TouchMeTokenCategory::String, length(residue), None),
+            Err(e) => (TouchMeTokenCategory::Error, recover(input), Some(e)),
+        }
+    } else if input.starts_with("/*") {
+        match block_comment(input) {
+            Ok((residue, _)) => (TouchMeTokenCategory::Comment, length(residue), None),
+            Err(e) => (TouchMeTokenCategory::Error, input.len(), Some(e)),
+        }
+    } else if let Ok((residue, _)) = line_comment(input) {
+        (TouchMeTokenCategory::Comment, length(residue), None)
+    } else if is_number_start(input) {
+        match number(input) {
+            Ok((residue, _)) => (TouchMeTokenCategory::Number, length(residue), None),
+            Err(e) => (TouchMeTokenCategory::Error, recover(input), Some(e)),
+        }
+    } else if let Ok((residue, _)) = macro_name(input) {
+        (TouchMeTokenCategory::Macro, length(residue), None)
+    } else if let Ok((residue, _)) = keyword(input) {
+        (TouchMeTokenCategory::Keyword, length(residue), None)
+    } else if let Ok((residue, _)) = identifier(input) {
+        (TouchMeTokenCategory::Identifier, length(residue), None)
+    } else if let Ok((residue, _)) = operators.operator(input) {
+        (TouchMeTokenCategory::Operator, length(residue), None)
+    } else {
+        let x = input.chars().next().map_or(0, char::len_utf8);
+        (TouchMeTokenCategory::Error, x, None)
+    }
+}
+
+/// byte length of a malformed string, up to its closing delimiter or
+/// else to the end of its line, or of a malformed number
+fn recover(input: &str) -> usize {
+    if is_number_start(input) {
+        let rest = input.trim_start_matches(|x: char| x.is_alphanumeric() || x == '_' || x == '.');
+        return input.len() - rest.len();
+    }
+    if raw_string_opening(input).is_some() {
+        return input.find('\n').unwrap_or(input.len());
+    }
+    let close = input.chars().next().and_then(closing_delimiter);
+    let mut chars = input.char_indices().skip(1);
+    while let Some((i, x)) = chars.next() {
+        match x {
+            '\\' => {
+                chars.next();
+            }
+            '\n' => return i,
+            x if Some(x) == close => return i + x.len_utf8(),
+            _ => {}
+        }
+    }
+    input.len()
+}
+
+impl Iterator for TouchMeTokenBundle {
+    type Item = TouchMeToken;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.pending.pop_front() {
+            return Some(token);
+        }
+        if self.done {
+            return None;
+        }
+
+        let residue = &self.buffer[self.offset..];
+        let blank = residue.len() - residue.trim_start().len();
+        self.line_start |= residue[..blank].contains('\n');
+        if self.trivia && blank > 0 {
+            let token = self.token(TouchMeTokenCategory::Whitespace, blank);
+            self.advance(blank);
+            return Some(token);
+        }
+        self.advance(blank);
+
+        let residue = &self.buffer[self.offset..];
+        if residue.is_empty() {
+            self.done = true;
+            if self.layout {
+                self.dedent(self.indents[0]);
+            }
+            let token = self.token(TouchMeTokenCategory::EOF, 0);
+            self.pending.push_back(token);
+            return self.pending.pop_front();
+        }
+
+        let (category, length, error) = classify(residue, self.hyphen, &self.operators);
+        let error = error.map(|e| (residue.len() - e.location.len(), e.expected));
+        if category != TouchMeTokenCategory::Comment {
+            if self.layout && self.line_start && self.depth == 0 {
+                self.line_start = false;
+                self.indent();
+                if let Some(token) = self.pending.pop_front() {
+                    return Some(token);
+                }
+            }
+            self.line_start = false;
+            self.started = true;
+        }
+        if let Some((at, expected)) = error {
+            let message = format!("expected {}", expected);
+            self.diagnose(Severity::Error, at, message);
+        } else if category == TouchMeTokenCategory::Error {
+            let message = format!("unexpected {:?}", &self.buffer[self.offset..][..length]);
+            self.diagnose(Severity::Error, 0, message);
+        }
+        let token = self.token(category, length);
+        if category == TouchMeTokenCategory::Identifier {
+            self.check_identifier(&token.value);
+        }
+        let value = token.canonical();
+        if category == TouchMeTokenCategory::Operator && value == "-" {
+            self.check_minus(length);
+        }
+        if self.width_warnings
+            && matches!(
+                category,
+                TouchMeTokenCategory::Operator | TouchMeTokenCategory::Number
+            )
+        {
+            self.check_width(&token.value);
+        }
+        if category == TouchMeTokenCategory::Operator {
+            match value.as_str() {
+                "(" | "[" => self.depth += 1,
+                ")" | "]" => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        self.advance(length);
+        Some(token)
+    }
+}
+
+pub fn lex(source: &str, filename: &str) -> Vec<TouchMeToken> {
+    TouchMeTokenBundle::new(source, filename).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TouchMeTokenCategory as T;
+
+    fn layout(source: &str) -> Vec<TouchMeToken> {
+        TouchMeTokenBundle::new(source, "test")
+            .with_layout()
+            .collect()
+    }
+
+    fn categories(source: &str) -> Vec<(T, String)> {
+        lex(source, "test")
+            .into_iter()
+            .map(|x| (x.category, x.value))
+            .collect()
+    }
+
+    #[test]
+    fn diagnostics_name_the_file() {
+        let mut bundle = TouchMeTokenBundle::new("\u{7}", "main.tm");
+        bundle.by_ref().for_each(drop);
+        let message = bundle.diagnostics()[0].to_string();
+        assert_eq!(message, "main.tm:1:1: error: unexpected \"\\u{7}\"");
+    }
+
+    #[test]
+    fn lexes_categories() {
+        let expected = [
+            (T::Keyword, "let"),
+            (T::Identifier, "x"),
+            (T::Operator, "="),
+            (T::Number, "1"),
+            (T::Operator, "+"),
+            (T::String, "\"a\""),
+            (T::Macro, "\\frac"),
+            (T::EOF, ""),
+        ];
+        let expected: Vec<_> = expected.iter().map(|&(x, y)| (x, y.to_string())).collect();
+        assert_eq!(categories("let x = 1 + \"a\"\n\\frac"), expected);
+    }
+
+    #[test]
+    fn tokens_carry_positions() {
+        let tokens = lex("a\n  bc", "main.tm");
+        let b = &tokens[1];
+        assert_eq!((b.lineno, b.columno, b.position, b.length), (2, 3, 4, 2));
+        assert_eq!(b.filename, "main.tm");
+        assert_eq!(b.locator(), Locator::new(2, 1));
+        let eof = &tokens[2];
+        assert_eq!((eof.category, eof.position, eof.length), (T::EOF, 6, 0));
+    }
+
+    #[test]
+    fn goes_on_after_errors() {
+        let tokens = categories("a \u{7} b");
+        assert_eq!(tokens[1], (T::Error, "\u{7}".to_string()));
+        assert_eq!(tokens[2], (T::Identifier, "b".to_string()));
+    }
+
+    #[test]
+    fn trivia_mode_covers_the_source() {
+        let source = "f  x /* y */\n\t1";
+        let tokens: Vec<_> = TouchMeTokenBundle::new(source, "test")
+            .with_trivia()
+            .collect();
+        let text: String = tokens.iter().map(|x| x.value.as_str()).collect();
+        assert_eq!(text, source);
+        assert!(tokens.iter().any(|x| x.category == T::Whitespace));
+        assert!(tokens.iter().all(|x| x.category != T::Error));
+    }
+
+    #[test]
+    fn lexes_from_a_start_in_an_enclosing_source() {
+        let tokens: Vec<_> = TouchMeTokenBundle::new("a\n b", "test")
+            .with_start(10, Locator::new(4, 2))
+            .collect();
+        assert_eq!(
+            (tokens[0].position, tokens[0].locator()),
+            (10, Locator::new(4, 2))
+        );
+        assert_eq!(
+            (tokens[1].position, tokens[1].locator()),
+            (13, Locator::new(1, 3))
+        );
+    }
+
+    #[test]
+    fn lexes_comments_as_single_tokens() {
+        let tokens = categories("a /* b /* c */ */ /// d\ne");
+        assert_eq!(tokens[1], (T::Comment, "/* b /* c */ */".to_string()));
+        assert_eq!(tokens[2], (T::Comment, "/// d".to_string()));
+        let tokens = categories("a /* b");
+        assert_eq!(tokens[1], (T::Error, "/* b".to_string()));
+    }
+
+    #[test]
+    fn lexes_layout_tokens() {
+        let layout: Vec<_> = layout("f =\n  a\n  b\n    c\nd")
+            .into_iter()
+            .map(|x| (x.category, x.value))
+            .collect();
+        let expected = [
+            (T::Identifier, "f"),
+            (T::Operator, "="),
+            (T::Indent, ""),
+            (T::Identifier, "a"),
+            (T::Newline, ""),
+            (T::Identifier, "b"),
+            (T::Indent, ""),
+            (T::Identifier, "c"),
+            (T::Dedent, ""),
+            (T::Dedent, ""),
+            (T::Newline, ""),
+            (T::Identifier, "d"),
+            (T::EOF, ""),
+        ];
+        let expected: Vec<_> = expected.map(|(x, y)| (x, y.to_string())).into();
+        assert_eq!(layout, expected);
+    }
+
+    #[test]
+    fn brackets_suspend_the_layout() {
+        let layout: Vec<_> = layout("f (a,\n    b)\n  c // x\n\n// y\n  d")
+            .into_iter()
+            .map(|x| x.category)
+            .filter(|x| matches!(x, T::Indent | T::Dedent | T::Newline))
+            .collect();
+        assert_eq!(layout, [T::Indent, T::Newline, T::Dedent]);
+    }
+
+    #[test]
+    fn layout_starts_at_the_column_of_the_first_line() {
+        let layout: Vec<_> = layout("  a\n  b\n    c")
+            .into_iter()
+            .map(|x| x.category)
+            .collect();
+        let expected = [
+            T::Identifier,
+            T::Newline,
+            T::Identifier,
+            T::Indent,
+            T::Identifier,
+            T::Dedent,
+            T::EOF,
+        ];
+        assert_eq!(layout, expected);
+    }
+
+    #[test]
+    fn reports_an_unmatched_unindent() {
+        let mut bundle = TouchMeTokenBundle::new("a\n    b\n  c", "test").with_layout();
+        bundle.by_ref().for_each(drop);
+        let errors: Vec<_> = bundle.diagnostics().iter().map(|x| &x.message).collect();
+        assert_eq!(
+            errors,
+            ["unindent does not match any outer indentation level"]
+        );
+    }
+
+    #[test]
+    fn keeps_the_outermost_block() {
+        let mut bundle = TouchMeTokenBundle::new("  a\n b", "test").with_layout();
+        let layout: Vec<_> = bundle.by_ref().map(|x| x.category).collect();
+        assert_eq!(layout, [T::Identifier, T::Newline, T::Identifier, T::EOF]);
+        let errors: Vec<_> = bundle.diagnostics().iter().map(|x| &x.message).collect();
+        assert_eq!(
+            errors,
+            ["unindent does not match any outer indentation level"]
+        );
+    }
+
+    #[test]
+    fn warns_about_confusable_identifiers() {
+        let mut bundle = TouchMeTokenBundle::new("paypal p\u{430}ypal paypal", "test");
+        let names: Vec<_> = bundle.by_ref().map(|x| x.value).collect();
+        assert_eq!(names[1], "p\u{430}ypal");
+        let messages: Vec<_> = bundle.diagnostics().iter().map(|x| &x.message).collect();
+        assert_eq!(
+            messages,
+            [
+                "identifier `p\u{430}ypal` mixes scripts",
+                "identifier `p\u{430}ypal` is confusable with `paypal`",
+            ]
+        );
+        assert_eq!(bundle.diagnostics()[0].severity, Severity::Warning);
+    }
+
+    fn warnings(bundle: TouchMeTokenBundle) -> Vec<String> {
+        let mut bundle = bundle;
+        bundle.by_ref().for_each(drop);
+        bundle
+            .diagnostics()
+            .iter()
+            .map(|x| x.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn warns_about_hyphens_read_as_subtractions() {
+        let source = "a b a-b";
+        let messages = warnings(TouchMeTokenBundle::new(source, "test"));
+        assert_eq!(
+            messages,
+            ["`a-b` is one identifier, write `a - b` for a subtraction"]
+        );
+        assert!(warnings(TouchMeTokenBundle::new("kebab-case", "test")).is_empty());
+
+        let numeric = TouchMeTokenBundle::new("x-1", "test").with_hyphen(Hyphen::Always);
+        assert_eq!(
+            warnings(numeric),
+            ["`x-1` is one identifier, write `x - 1` for a subtraction"]
+        );
+    }
+
+    #[test]
+    fn lexes_hyphens_by_the_rule() {
+        let values = |hyphen| -> Vec<String> {
+            TouchMeTokenBundle::new("a-b x-1", "test")
+                .with_hyphen(hyphen)
+                .map(|x| x.value)
+                .collect()
+        };
+        assert_eq!(values(Hyphen::BetweenLetters), ["a-b", "x", "-", "1", ""]);
+        assert_eq!(values(Hyphen::Always), ["a-b", "x-1", ""]);
+        assert_eq!(values(Hyphen::Never), ["a", "-", "b", "x", "-", "1", ""]);
+    }
+
+    #[test]
+    fn warns_about_lopsided_minus() {
+        let messages = warnings(TouchMeTokenBundle::new("a -b (-c) d - e", "test"));
+        assert_eq!(
+            messages,
+            ["`-` has a space on one side only, it may not be the minus meant"]
+        );
+    }
 
+    #[test]
+    fn lexes_full_width_forms() {
+        let tokens = lex("ｆ（１，２）", "test");
+        let canonical: Vec<_> = tokens.iter().map(|x| (x.category, x.canonical())).collect();
+        assert_eq!(canonical[1], (T::Operator, "(".to_string()));
+        assert_eq!(canonical[2], (T::Number, "1".to_string()));
+        assert_eq!(canonical[3], (T::Operator, ",".to_string()));
+        // the source text is kept in the tokens
+        assert_eq!(tokens[1].value, "（");
+        assert_eq!(tokens[1].length, 3);
+    }
 
+    #[test]
+    fn warns_about_mixed_widths() {
+        let mixed = TouchMeTokenBundle::new("1 + ２", "test").with_width_warnings();
+        assert_eq!(warnings(mixed), ["full-width `２` among half-width forms"]);
+        let full = TouchMeTokenBundle::new("１ ＋ ２", "test").with_width_warnings();
+        assert!(warnings(full).is_empty());
+        assert!(warnings(TouchMeTokenBundle::new("1 + ２", "test")).is_empty());
+    }
 
+    #[test]
+    fn lexes_the_longest_operator() {
+        let values: Vec<_> = lex("a<=b**c|>d", "test")
+            .into_iter()
+            .map(|x| x.value)
+            .collect();
+        assert_eq!(values, ["a", "<=", "b", "**", "c", "|", ">", "d", ""]);
+        let mut operators = OperatorRegistry::new();
+        assert!(operators.insert("<=>"));
+        let mut bundle = TouchMeTokenBundle::new("a<=>b<=c", "test").with_operators(operators);
+        let values: Vec<_> = bundle.by_ref().map(|x| x.value).collect();
+        assert_eq!(values, ["a", "<=>", "b", "<", "=", "c", ""]);
+        assert!(bundle.operators().contains("<=>"));
+    }
+}
@@ -1,38 +1,11 @@
-// enum TouchMeTokenCategory {
-//     EOF,
-//     Error,
-//     Comment,
-//     String,
-//     Number,
-//     Identifier,
-//     Macro,
-// }
-
-// struct TouchMeToken {
-//     category: TouchMeTokenCategory,
-//     lineno: u32,
-//     columno: u32,
-//     position: u32,
-//     length: u32,
-//     filename: String,
-//     value: String,
-// }
-
-// pub struct TouchMeTokenBundle {
-//     buffer: String,
-//     offset: u32,
-//     position: u32,
-//     length: u32,
-//     lineno: u32,
-//     columno: u32,
-//     filename: String,
-
-//     cache: TouchMeToken,
-// }
-
-use super::parser::{
-    combinators,
-    parsec::{self, character, either3, plus, token, twice},
+use std::collections::BTreeSet;
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_security::{RestrictionLevel, RestrictionLevelDetection};
+
+use super::{
+    bigint::BigInt,
+    parser::parsec::{self, character},
 };
 
 pub fn is_builtin_operator(x: char) -> bool {
@@ -53,76 +26,983 @@ pub fn is_builtin_operator(x: char) -> bool {
         || (x == ':')
         || (x == '?')
         || (x == ',')
+        || (x == ';')
         || (x == '[')
         || (x == ']')
         || (x == '(')
         || (x == ')')
+        || ascii_alias(x).is_some_and(is_builtin_operator)
+}
+
+/// operators of `OperatorRegistry::default`, besides each single character
+/// of `is_builtin_operator`
+pub const BUILTIN_OPERATORS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "<<", ">>", "->", "=>", "**",
+];
+
+/// whether `x` may be part of an operator of an `OperatorRegistry`
+pub fn is_operator_char(x: char) -> bool {
+    "+-*/<>!=|&^%~.:?@".contains(unalias(x))
+}
+
+/// match the longest of `operators` at the start of `input`, also in
+/// full-width forms, yields its ASCII form
+fn longest_operator<'a, 'o>(
+    operators: impl Iterator<Item = &'o str>,
+    input: &'a str,
+) -> Result<(&'a str, String), parsec::ParseError<'a>> {
+    let prefix = |operator: &str| {
+        let mut chars = input.chars();
+        for x in operator.chars() {
+            if chars.next().map(unalias) != Some(x) {
+                return None;
+            }
+        }
+        Some(chars.as_str())
+    };
+    let mut chars = input.chars();
+    let single = match chars.next() {
+        Some(x) if is_builtin_operator(x) => Some((chars.as_str(), unalias(x).to_string())),
+        _ => None,
+    };
+    operators
+        .filter_map(|x| prefix(x).map(|residue| (residue, x.to_string())))
+        .chain(single)
+        .min_by_key(|(residue, _)| residue.len())
+        .ok_or_else(|| parsec::ParseError::new(input, "operator"))
 }
 
-pub fn builtin_operator(input: &str) -> Result<(&str, String), parsec::ParseError> {
-    combinators::leak(token(is_builtin_operator))(input)
+/// symbolic operators which the lexer matches by maximal munch, e.g. `==`
+/// rather than `=` and `=`
+///
+/// the default one holds `BUILTIN_OPERATORS`, a library can insert more and
+/// give them to `TouchMeTokenBundle::with_operators`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OperatorRegistry {
+    operators: BTreeSet<String>,
+}
+
+impl Default for OperatorRegistry {
+    fn default() -> Self {
+        OperatorRegistry {
+            operators: BUILTIN_OPERATORS.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+}
+
+impl OperatorRegistry {
+    /// `is_builtin_operator` characters only
+    pub fn new() -> Self {
+        OperatorRegistry {
+            operators: BTreeSet::new(),
+        }
+    }
+
+    /// add `operator`, which must consist of `is_operator_char`, returns
+    /// whether it was added
+    pub fn insert(&mut self, operator: &str) -> bool {
+        !operator.is_empty()
+            && operator.chars().all(is_operator_char)
+            && self.operators.insert(canonical(operator))
+    }
+
+    pub fn contains(&self, operator: &str) -> bool {
+        self.operators.contains(&canonical(operator))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.operators.iter().map(String::as_str)
+    }
+
+    /// match the longest operator at the start of `input`, yields its
+    /// ASCII form
+    pub fn operator<'a>(
+        &self,
+        input: &'a str,
+    ) -> Result<(&'a str, String), parsec::ParseError<'a>> {
+        longest_operator(self.iter(), input)
+    }
+}
+
+/// ASCII form of a full-width character or of `、`, e.g. `（` is `(` and
+/// `１` is `1`, so that one need not leave the input mode of an IME
+pub fn ascii_alias(x: char) -> Option<char> {
+    match x {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(x as u32 - 0xFEE0),
+        '、' => Some(','),
+        _ => None,
+    }
+}
+
+/// `x` in its ASCII form if it has one
+pub fn unalias(x: char) -> char {
+    ascii_alias(x).unwrap_or(x)
+}
+
+/// `text` with its full-width characters in their ASCII forms
+pub fn canonical(text: &str) -> String {
+    text.chars().map(unalias).collect()
+}
+
+/// `input` without its first character, if that is `expected` or its
+/// full-width form
+fn strip_alias(input: &str, expected: char) -> Option<&str> {
+    let mut chars = input.chars();
+    match chars.next() {
+        Some(x) if unalias(x) == expected => Some(chars.as_str()),
+        _ => None,
+    }
 }
 
 pub fn is_quotes(x: char) -> bool {
     (x == '"') || (x == '\'')
 }
 
-pub fn quote(input: &str) -> Result<(&str, String), parsec::ParseError> {
+pub fn quote(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
     parsec::token(is_quotes)(input)
 }
 
-pub fn left_corner_bracket(input: &str) -> Result<(&str, String), parsec::ParseError> {
+pub fn left_corner_bracket(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
     character('「')(input)
 }
 
-pub fn right_corner_bracket(input: &str) -> Result<(&str, String), parsec::ParseError> {
+pub fn right_corner_bracket(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
     character('」')(input)
 }
 
-pub fn left_and_right_corner_bracket(input: &str) -> Result<(&str, String), parsec::ParseError> {
+pub fn left_and_right_corner_bracket(
+    input: &str,
+) -> Result<(&str, String), parsec::ParseError<'_>> {
     parsec::append(left_corner_bracket, right_corner_bracket)(input)
 }
 
-pub fn string_of<X>(
-    parser: impl Fn(&str) -> Result<(&str, X), parsec::ParseError>,
-) -> impl Fn(&str) -> Result<(&str, X), parsec::ParseError> {
-    let quote_left = parsec::either(quote, left_corner_bracket);
-    let quote_right = parsec::either(quote, right_corner_bracket);
-    parsec::between(quote_left, quote_right, parser)
+/// closing delimiter of the string opened by `x`
+pub fn closing_delimiter(x: char) -> Option<char> {
+    match x {
+        '\'' | '"' => Some(x),
+        '「' => Some('」'),
+        '『' => Some('』'),
+        _ => None,
+    }
+}
+
+/// match an escape sequence starting with `\`
+/// - `\n`, `\t`, `\r`, `\0`, `\\`, `\'`, `\"`, `\「`, `\」`
+/// - `\u{...}` of 1 to 6 hex digits
+/// - `\` at the end of a line, which skips the line break and the
+///   indentation of the next line and yields nothing
+pub fn escape(input: &str) -> Result<(&str, Option<char>), parsec::ParseError<'_>> {
+    let residue = match input.strip_prefix('\\') {
+        Some(x) => x,
+        None => return Err(parsec::ParseError::new(input, "escape sequence")),
+    };
+    let mut chars = residue.chars();
+    let x = match chars.next() {
+        Some('n') => '\n',
+        Some('t') => '\t',
+        Some('r') => '\r',
+        Some('0') => '\0',
+        Some(x) if matches!(x, '\\' | '\'' | '"' | '$' | '「' | '」' | '『' | '』') => x,
+        Some('u') => return unicode_escape(input, chars.as_str()),
+        Some('\n') => return Ok((chars.as_str().trim_start_matches([' ', '\t']), None)),
+        Some('\r') if chars.as_str().starts_with('\n') => {
+            let residue = &chars.as_str()[1..];
+            return Ok((residue.trim_start_matches([' ', '\t']), None));
+        }
+        Some(x) => {
+            return Err(parsec::ParseError {
+                location: input,
+                expected: format!(
+                    concat!(
+                        "escape `\\n`, `\\t`, `\\r`, `\\0`, `\\\\`, `\\$`, a quote ",
+                        "or `\\u{{...}}`, found `\\{}`",
+                    ),
+                    x.escape_debug()
+                ),
+            })
+        }
+        None => return Err(parsec::ParseError::new(input, "escape after `\\`")),
+    };
+    Ok((chars.as_str(), Some(x)))
 }
 
-/// must be not empty
-pub fn valid_string_content(input: &str) -> Result<(&str, String), parsec::ParseError> {
-    plus(token(|x| x != '\'' && x != '"' && x != '」'))(input)
+/// `{...}` of `\u{...}`, `input` points at the backslash for diagnostics
+fn unicode_escape<'a>(
+    input: &'a str,
+    residue: &'a str,
+) -> Result<(&'a str, Option<char>), parsec::ParseError<'a>> {
+    let digits = match residue.strip_prefix('{') {
+        Some(x) => x,
+        None => return Err(parsec::ParseError::new(residue, "`{` after `\\u`")),
+    };
+    let length = digits.len()
+        - digits
+            .trim_start_matches(|x: char| x.is_ascii_hexdigit())
+            .len();
+    let residue = &digits[length..];
+    if length == 0 {
+        return Err(parsec::ParseError::new(digits, "hex digit in `\\u{...}`"));
+    }
+    if length > 6 {
+        return Err(parsec::ParseError::new(
+            &digits[6..],
+            "`}`, `\\u{...}` takes at most 6 hex digits",
+        ));
+    }
+    let residue = match residue.strip_prefix('}') {
+        Some(x) => x,
+        None => return Err(parsec::ParseError::new(residue, "`}` closing `\\u{...}`")),
+    };
+    match u32::from_str_radix(&digits[..length], 16)
+        .ok()
+        .and_then(char::from_u32)
+    {
+        Some(x) => Ok((residue, Some(x))),
+        None => Err(parsec::ParseError {
+            location: input,
+            expected: format!(
+                "unicode scalar value, `{}` is a surrogate or above 10FFFF",
+                &digits[..length]
+            ),
+        }),
+    }
 }
 
-/// match string of
-/// - empty `''` or `""` or `「」`
-/// - `valid_string_content`
-pub fn string(input: &str) -> Result<(&str, String), parsec::ParseError> {
-    // of(quote)
-    //     .twice()
-    //     .either(of(left_and_right_corner_bracket))
-    //     .either(of(string_of(valid_string_content)))
-    //     .parse(input)
+/// match a string between matching delimiters, `'...'`, `"..."`, `「...」`
+/// or `『...』`, and yield its content with the escape sequences resolved
+///
+/// brackets nest inside bracket strings and are kept in the content, e.g.
+/// `「彼は『こんにちは』と言った」`, but they must be balanced
+///
+/// errors point at the offending escape sequence or bracket, or at the
+/// opening delimiter of an unterminated string
+pub fn string(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
+    let (residue, segments) = template(input)?;
+    let mut value = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Text(x) => value += &x,
+            Segment::Code(code) => {
+                return Err(parsec::ParseError::new(
+                    code,
+                    "string without `${...}`, escape `$` as `\\$`",
+                ))
+            }
+        }
+    }
+    Ok((residue, value))
+}
 
-    either3(
-        twice(quote),
-        left_and_right_corner_bracket,
-        string_of(valid_string_content),
-    )(input)
+/// part of a template string
+#[derive(Clone, Debug, PartialEq)]
+pub enum Segment<'a> {
+    Text(String),
+    /// source of an embedded expression, between `${` and `}`
+    Code(&'a str),
 }
 
+/// match a string like `string`, in which `${...}` embeds an expression,
+/// yields its text and the sources of its embedded expressions
+///
+/// e.g. `"total: ${a + b}"` or `「こんにちは、${name}」`
+pub fn template(input: &str) -> Result<(&str, Vec<Segment<'_>>), parsec::ParseError<'_>> {
+    let mut chars = input.chars();
+    let close = match chars.next().and_then(closing_delimiter) {
+        Some(x) => x,
+        None => return Err(parsec::ParseError::new(input, "opening quote")),
+    };
+    let nested = close == '」' || close == '』';
+
+    let mut segments = Vec::new();
+    let mut value = String::new();
+    let mut brackets = Vec::new();
+    let mut residue = chars.as_str();
+    loop {
+        let mut chars = residue.chars();
+        match chars.next() {
+            Some(x) if x == close && brackets.is_empty() => {
+                if !value.is_empty() || segments.is_empty() {
+                    segments.push(Segment::Text(value));
+                }
+                return Ok((chars.as_str(), segments));
+            }
+            Some('\\') => {
+                let (next, x) = escape(residue)?;
+                value.extend(x);
+                residue = next;
+                continue;
+            }
+            Some('$') if chars.as_str().starts_with('{') => {
+                if !value.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut value)));
+                }
+                let (next, code) = interpolation(residue)?;
+                segments.push(Segment::Code(code));
+                residue = next;
+                continue;
+            }
+            Some(x @ ('「' | '『')) if nested => brackets.extend(closing_delimiter(x)),
+            Some(x @ ('」' | '』')) if nested => match brackets.pop() {
+                Some(y) if x == y => {}
+                y => {
+                    return Err(parsec::ParseError {
+                        location: residue,
+                        expected: format!("`{}` to close the nested bracket", y.unwrap_or(close)),
+                    })
+                }
+            },
+            Some(_) => {}
+            None => {
+                return Err(parsec::ParseError {
+                    location: input,
+                    expected: format!("closing `{}` of this string", close),
+                })
+            }
+        }
+        value.extend(residue.chars().next());
+        residue = chars.as_str();
+    }
+}
+
+/// source of the expression embedded by `${...}` at the start of `input`,
+/// nested braces and strings are passed over
+fn interpolation(input: &str) -> Result<(&str, &str), parsec::ParseError<'_>> {
+    let code = &input[2..];
+    let mut depth = 0;
+    let mut residue = code;
+    loop {
+        if let Ok((next, _)) = identifier(residue) {
+            residue = next;
+            continue;
+        }
+        if is_string_start(residue) {
+            residue = template_literal(residue)?.0;
+            continue;
+        }
+        let mut chars = residue.chars();
+        match chars.next() {
+            Some('{') => depth += 1,
+            Some('}') if depth == 0 => {
+                return Ok((chars.as_str(), &code[..code.len() - residue.len()]));
+            }
+            Some('}') => depth -= 1,
+            Some(_) => {}
+            None => {
+                return Err(parsec::ParseError::new(
+                    input,
+                    "closing `}` of this interpolation",
+                ))
+            }
+        }
+        residue = chars.as_str();
+    }
+}
+
+/// opening of a raw string, `r` and some `#` before a quote or a bracket,
+/// yields the content and the closing delimiter, e.g. `"##` of `r##"`
+pub fn raw_string_opening(input: &str) -> Option<(&str, String)> {
+    let residue = input.strip_prefix('r')?;
+    let content = residue.trim_start_matches('#');
+    let hashes = &residue[..residue.len() - content.len()];
+    let mut chars = content.chars();
+    let close = chars.next().and_then(closing_delimiter)?;
+    Some((chars.as_str(), format!("{}{}", close, hashes)))
+}
+
+/// match a raw string, which has no escape sequences
+///
+/// e.g. `r"C:\path"`, `r'it"s'`, `r#"say "hi""#` or `r「\(^o^)/」`
+pub fn raw_string(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
+    let (content, close) = match raw_string_opening(input) {
+        Some(x) => x,
+        None => return Err(parsec::ParseError::new(input, "raw string")),
+    };
+    match content.find(&close) {
+        Some(i) => Ok((&content[i + close.len()..], content[..i].to_string())),
+        None => Err(parsec::ParseError {
+            location: input,
+            expected: format!("closing `{}` of this raw string", close),
+        }),
+    }
+}
+
+/// whether `input` starts with a raw or an escaped string
+pub fn is_string_start(input: &str) -> bool {
+    raw_string_opening(input).is_some()
+        || input.chars().next().and_then(closing_delimiter).is_some()
+}
+
+/// match a raw string or a template string
+pub fn template_literal(input: &str) -> Result<(&str, Vec<Segment<'_>>), parsec::ParseError<'_>> {
+    match raw_string_opening(input) {
+        Some(_) => parsec::map(raw_string, |x| vec![Segment::Text(x)])(input),
+        None => template(input),
+    }
+}
+
+/// `XID_Start` of UAX #31, `_` or `$`
 pub fn is_identifier_head(x: char) -> bool {
-    x.is_alphabetic() || x == '_' || x == '$'
+    unicode_ident::is_xid_start(x) || x == '_' || x == '$'
 }
 
+/// `XID_Continue` of UAX #31, `$` or `'`, see `Hyphen` for `-`
 pub fn is_identifier_body(x: char) -> bool {
-    is_identifier_head(x) || x.is_ascii_digit() || x == '-' || x == '\''
+    unicode_ident::is_xid_continue(x) || x == '$' || x == '\''
+}
+
+/// whether `-` inside a word is part of an identifier or a minus
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Hyphen {
+    /// `-` between two letters only, `kebab-case` is an identifier while
+    /// `x-1` is `x - 1`
+    #[default]
+    BetweenLetters,
+    /// `-` before any identifier character, `x-1` is an identifier and a
+    /// binary minus needs spaces
+    Always,
+    /// `-` is always a minus, `a-b` is `a - b`
+    Never,
+}
+
+impl Hyphen {
+    /// whether a `-` between `before` and `after` joins them
+    pub fn joins(self, before: char, after: Option<char>) -> bool {
+        match self {
+            Hyphen::BetweenLetters => {
+                before.is_alphabetic() && after.is_some_and(char::is_alphabetic)
+            }
+            Hyphen::Always => after.is_some_and(is_identifier_body),
+            Hyphen::Never => false,
+        }
+    }
+}
+
+/// NFC of `name`, so that canonically equivalent names are equal
+pub fn normalize(name: &str) -> String {
+    name.nfc().collect()
+}
+
+/// match an identifier, yields it in NFC
+pub fn identifier(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
+    identifier_with(Hyphen::default())(input)
 }
 
-pub fn identifier(input: &str) -> Result<(&str, String), parsec::ParseError> {
-    let head = parsec::token(is_identifier_head);
-    let body = parsec::asterisk(parsec::token(is_identifier_body));
-    parsec::map(parsec::follow(head, body), |(s, t)| s + &t)(input)
+/// reserved words, which are not identifiers
+pub const KEYWORDS: &[&str] = &[
+    "let", "rec", "def", "if", "then", "else", "match", "with", "true", "false",
+];
+
+pub fn is_keyword(name: &str) -> bool {
+    KEYWORDS.contains(&name)
+}
+
+/// match a keyword, but not an identifier which starts with one, e.g. not
+/// `letter` or `let-x`
+pub fn keyword(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
+    keyword_with(Hyphen::default())(input)
+}
+
+/// `keyword` with the given rule for `-` in identifiers
+pub fn keyword_with(hyphen: Hyphen) -> impl Fn(&str) -> Result<(&str, String), parsec::ParseError> {
+    move |input| match name_with(hyphen)(input) {
+        Ok((residue, x)) if is_keyword(&x) => Ok((residue, x)),
+        _ => Err(parsec::ParseError::new(input, "keyword")),
+    }
+}
+
+/// match an identifier with the given rule for `-`, yields it in NFC
+pub fn identifier_with(
+    hyphen: Hyphen,
+) -> impl Fn(&str) -> Result<(&str, String), parsec::ParseError> {
+    move |input| match name_with(hyphen)(input) {
+        Ok((_, x)) if is_keyword(&x) => Err(parsec::ParseError::new(input, "identifier")),
+        x => x,
+    }
+}
+
+/// match an identifier or a keyword
+fn name_with(hyphen: Hyphen) -> impl Fn(&str) -> Result<(&str, String), parsec::ParseError> {
+    move |input| {
+        let mut chars = input.chars();
+        let mut previous = match chars.next() {
+            Some(x) if is_identifier_head(x) => x,
+            _ => return Err(parsec::ParseError::new(input, "identifier")),
+        };
+        loop {
+            let mut ahead = chars.clone();
+            match ahead.next() {
+                Some(x) if is_identifier_body(x) => previous = x,
+                Some('-') if hyphen.joins(previous, ahead.clone().next()) => previous = '-',
+                _ => break,
+            }
+            chars = ahead;
+        }
+        let residue = chars.as_str();
+        Ok((residue, normalize(&input[..input.len() - residue.len()])))
+    }
+}
+
+/// why `name` may be misread, by the restriction levels of UTS #39
+///
+/// Latin may be mixed with Han, Hiragana and Katakana, as in `user名`,
+/// but not with another script, as in `pаypal` with a Cyrillic `а`
+pub fn identifier_warning(name: &str) -> Option<String> {
+    // `$`, `-` and `'` are of this language rather than of Unicode
+    let letters: String = name.chars().filter(|x| !"$-'".contains(*x)).collect();
+    match letters.as_str().detect_restriction_level() {
+        RestrictionLevel::ASCIIOnly
+        | RestrictionLevel::SingleScript
+        | RestrictionLevel::HighlyRestrictive => None,
+        RestrictionLevel::Unrestricted => Some(format!(
+            "identifier `{}` has characters not recommended in identifiers",
+            name
+        )),
+        _ => Some(format!("identifier `{}` mixes scripts", name)),
+    }
+}
+
+/// confusable skeleton of UTS #39, identifiers which look alike have the
+/// same skeleton
+pub fn skeleton(name: &str) -> String {
+    unicode_security::skeleton(name).collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Number {
+    Integer(i64),
+    /// integer which does not fit in an `i64`
+    BigInteger(BigInt),
+    Float(f64),
+}
+
+/// digits of `radix` separated by single `_`, yields them without the
+/// separators
+fn digits<'a>(
+    input: &'a str,
+    radix: u32,
+    kind: &str,
+) -> Result<(&'a str, String), parsec::ParseError<'a>> {
+    let mut value = String::new();
+    let mut residue = input;
+    loop {
+        let mut chars = residue.chars();
+        match chars.next().map(unalias) {
+            Some(x) if x.is_digit(radix) => value.push(x),
+            Some('_') if !value.is_empty() => match chars.clone().next().map(unalias) {
+                Some(x) if x.is_digit(radix) => {}
+                _ => {
+                    return Err(parsec::ParseError {
+                        location: chars.as_str(),
+                        expected: format!("{} digit after `_`", kind),
+                    })
+                }
+            },
+            _ if value.is_empty() => {
+                return Err(parsec::ParseError {
+                    location: residue,
+                    expected: format!("{} digit", kind),
+                })
+            }
+            _ => return Ok((residue, value)),
+        }
+        residue = chars.as_str();
+    }
+}
+
+/// fails if a number is directly followed by a letter or a digit
+fn end_of_number<'a>(input: &'a str, kind: &str) -> Result<&'a str, parsec::ParseError<'a>> {
+    match input.chars().next() {
+        Some(x) if x.is_alphanumeric() || x == '_' => Err(parsec::ParseError {
+            location: input,
+            expected: format!("end of {} literal, found `{}`", kind, x),
+        }),
+        _ => Ok(input),
+    }
+}
+
+fn integer(digits: &str, radix: u32) -> Number {
+    match i64::from_str_radix(digits, radix) {
+        Ok(x) => Number::Integer(x),
+        Err(_) => Number::BigInteger(BigInt::from_digits(digits, radix).unwrap_or_default()),
+    }
+}
+
+/// whether `input` starts with a digit, or its full-width form
+pub fn is_number_start(input: &str) -> bool {
+    input.starts_with(|x: char| unalias(x).is_ascii_digit())
+}
+
+/// match a numeric literal
+/// - decimal integers `42`, `1_000_000`
+/// - `0x`, `0o` and `0b` integers, `0xff_ff`
+/// - floats `3.14`, `1e-9`, `6.022_140e23`
+///
+/// `_` separates digits only, integers which do not fit in an `i64` become
+/// `Number::BigInteger`, floats out of the range of `f64` are rejected,
+/// full-width digits are read as ASCII ones
+pub fn number(input: &str) -> Result<(&str, Number), parsec::ParseError<'_>> {
+    let mut chars = input.chars();
+    let prefix: String = chars.by_ref().take(2).map(unalias).collect();
+    let radix = match prefix.as_str() {
+        "0x" | "0X" => Some((16, "hexadecimal")),
+        "0o" | "0O" => Some((8, "octal")),
+        "0b" | "0B" => Some((2, "binary")),
+        _ => None,
+    };
+    if let Some((radix, kind)) = radix {
+        let (residue, value) = digits(chars.as_str(), radix, kind)?;
+        return Ok((end_of_number(residue, kind)?, integer(&value, radix)));
+    }
+
+    let (mut residue, mut text) = digits(input, 10, "decimal")?;
+    let mut float = false;
+    if let Some(fraction) = strip_alias(residue, '.') {
+        if is_number_start(fraction) {
+            let (next, value) = digits(fraction, 10, "decimal")?;
+            text = format!("{}.{}", text, value);
+            residue = next;
+            float = true;
+        }
+    }
+    if let Some(exponent) = strip_alias(residue, 'e').or_else(|| strip_alias(residue, 'E')) {
+        let (exponent, sign) = match (strip_alias(exponent, '+'), strip_alias(exponent, '-')) {
+            (Some(x), _) => (x, "+"),
+            (_, Some(x)) => (x, "-"),
+            _ => (exponent, ""),
+        };
+        let (next, value) = digits(exponent, 10, "exponent")?;
+        text = format!("{}e{}{}", text, sign, value);
+        residue = next;
+        float = true;
+    }
+
+    match float {
+        true => match text.parse::<f64>() {
+            Ok(x) if x.is_finite() => Ok((end_of_number(residue, "float")?, Number::Float(x))),
+            _ => Err(parsec::ParseError::new(
+                input,
+                "float literal within the range of f64",
+            )),
+        },
+        false => Ok((end_of_number(residue, "decimal")?, integer(&text, 10))),
+    }
+}
+
+/// match a `//` comment up to the end of its line, yields its text after `//`
+pub fn line_comment(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
+    let content = match input.strip_prefix("//") {
+        Some(x) => x,
+        None => return Err(parsec::ParseError::new(input, "`//`")),
+    };
+    let end = content.find('\n').unwrap_or(content.len());
+    Ok((&content[end..], content[..end].to_string()))
+}
+
+/// match a `/* */` comment, which may nest, yields its text between the
+/// outermost delimiters
+pub fn block_comment(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
+    let content = match input.strip_prefix("/*") {
+        Some(x) => x,
+        None => return Err(parsec::ParseError::new(input, "`/*`")),
+    };
+    let mut depth = 1;
+    let mut residue = content;
+    while depth > 0 {
+        if let Some(next) = residue.strip_prefix("*/") {
+            depth -= 1;
+            residue = next;
+        } else if let Some(next) = residue.strip_prefix("/*") {
+            depth += 1;
+            residue = next;
+        } else {
+            let mut chars = residue.chars();
+            if chars.next().is_none() {
+                return Err(parsec::ParseError::new(
+                    input,
+                    "closing `*/` of this block comment",
+                ));
+            }
+            residue = chars.as_str();
+        }
+    }
+    let text = &content[..content.len() - residue.len() - 2];
+    Ok((residue, text.to_string()))
+}
+
+/// match a `///` doc comment, but not `////`, yields its text without the
+/// slashes and one leading space
+pub fn doc_comment(input: &str) -> Result<(&str, String), parsec::ParseError<'_>> {
+    match input.strip_prefix("///") {
+        Some(x) if !x.starts_with('/') => {
+            let (residue, text) = line_comment(&input[1..])?;
+            let text = text.strip_prefix(' ').unwrap_or(&text);
+            Ok((residue, text.to_string()))
+        }
+        _ => Err(parsec::ParseError::new(input, "`///`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(input: &str) -> Option<String> {
+        let x = match raw_string_opening(input) {
+            Some(_) => raw_string(input),
+            None => string(input),
+        };
+        x.ok().map(|(_, x)| x)
+    }
+
+    #[test]
+    fn strings_need_matching_delimiters() {
+        assert_eq!(string("\"it's\" x"), Ok((" x", "it's".to_string())));
+        assert_eq!(string("'say \"hi\"'"), Ok(("", "say \"hi\"".to_string())));
+        assert_eq!(value("\"\""), Some("".to_string()));
+        let error = string("\"abc'").unwrap_err();
+        assert_eq!(error.location, "\"abc'");
+        assert_eq!(error.expected, "closing `\"` of this string");
+        assert!(string("abc").is_err());
+    }
+
+    #[test]
+    fn resolves_escape_sequences() {
+        let escaped = r#""a\nb\t\r\0\\\'\"\「\」""#;
+        assert_eq!(value(escaped).as_deref(), Some("a\nb\t\r\0\\'\"「」"));
+        assert_eq!(value(r#""\u{3042}\u{1F600}""#).as_deref(), Some("あ😀"));
+        assert_eq!(value("\"a\\\n    b\"").as_deref(), Some("ab"));
+        assert_eq!(value("\"a\\\r\n\tb\"").as_deref(), Some("ab"));
+    }
+
+    #[test]
+    fn rejects_malformed_escapes() {
+        let error = string(r#""a\qb""#).unwrap_err();
+        assert_eq!(error.location, r#"\qb""#);
+        assert!(error.expected.ends_with("found `\\q`"));
+        assert!(string(r#""\あ""#).is_err());
+        assert!(string(r#""\u{}""#).is_err());
+        assert!(string(r#""\u{1234567}""#).is_err());
+        let error = string(r#""\u{D800}""#).unwrap_err();
+        assert!(error.expected.contains("surrogate"));
+    }
+
+    #[test]
+    fn nests_corner_brackets() {
+        let nested = "「彼は『こんにちは』と言った」";
+        assert_eq!(value(nested).as_deref(), Some("彼は『こんにちは』と言った"));
+        assert_eq!(value("『「a」「b」』").as_deref(), Some("「a」「b」"));
+        // quotes do not nest, brackets are plain text in them
+        assert_eq!(value("\"「\"").as_deref(), Some("「"));
+        let error = string("「a』」").unwrap_err();
+        assert_eq!(error.location, "』」");
+        assert!(string("「a「b」").is_err());
+    }
+
+    #[test]
+    fn raw_strings_keep_backslashes() {
+        assert_eq!(value(r#"r"C:\path""#).as_deref(), Some(r"C:\path"));
+        assert_eq!(value(r#"r'it"s'"#).as_deref(), Some("it\"s"));
+        assert_eq!(value(r###"r#"say "hi""#"###).as_deref(), Some("say \"hi\""));
+        assert_eq!(value(r"r「\(^o^)/」").as_deref(), Some(r"\(^o^)/"));
+        let error = raw_string(r##"r#"a""##).unwrap_err();
+        assert_eq!(error.expected, "closing `\"#` of this raw string");
+        assert!(is_string_start("r'x'"));
+        assert!(!is_string_start("r x"));
+    }
+
+    fn read(input: &str) -> Number {
+        match number(input) {
+            Ok(("", x)) => x,
+            x => panic!("{:?}", x),
+        }
+    }
+
+    #[test]
+    fn reads_integers() {
+        assert_eq!(read("42"), Number::Integer(42));
+        assert_eq!(read("1_000_000"), Number::Integer(1_000_000));
+        assert_eq!(read("0xff_ff"), Number::Integer(0xffff));
+        assert_eq!(read("0o17"), Number::Integer(0o17));
+        assert_eq!(read("0B101"), Number::Integer(5));
+        assert_eq!(read("４２"), Number::Integer(42));
+        assert_eq!(number("1 + 2"), Ok((" + 2", Number::Integer(1))));
+    }
+
+    #[test]
+    fn reads_big_integers() {
+        let big = "123456789012345678901234567890";
+        assert_eq!(
+            read(big),
+            Number::BigInteger(BigInt::from_digits(big, 10).unwrap())
+        );
+        assert_eq!(read("9223372036854775807"), Number::Integer(i64::MAX));
+        match read("0xffff_ffff_ffff_ffff_ffff") {
+            Number::BigInteger(x) => assert_eq!(x.to_string(), "1208925819614629174706175"),
+            x => panic!("{:?}", x),
+        }
+    }
+
+    #[test]
+    fn reads_floats() {
+        assert_eq!(read("2.5"), Number::Float(2.5));
+        assert_eq!(read("1e-9"), Number::Float(1e-9));
+        assert_eq!(read("6.022_140e23"), Number::Float(6.022_140e23));
+        assert_eq!(read("2E+3"), Number::Float(2000.0));
+        // a method call or a range, not a fraction
+        assert_eq!(number("1.x"), Ok((".x", Number::Integer(1))));
+    }
+
+    #[test]
+    fn rejects_malformed_numbers() {
+        let error = number("1__0").unwrap_err();
+        assert_eq!(
+            (error.location, error.expected.as_str()),
+            ("_0", "decimal digit after `_`")
+        );
+        assert!(number("1_").is_err());
+        assert!(number("0x").is_err());
+        assert!(number("0b102").is_err());
+        assert!(number("12abc").is_err());
+        assert!(number("1e").is_err());
+        let error = number("1e999").unwrap_err();
+        assert_eq!(error.expected, "float literal within the range of f64");
+    }
+
+    #[test]
+    fn reads_line_and_block_comments() {
+        assert_eq!(line_comment("// a\nb"), Ok(("\nb", " a".to_string())));
+        assert_eq!(block_comment("/* a */b"), Ok(("b", " a ".to_string())));
+        let nested = "/* a /* b */ c */d";
+        assert_eq!(
+            block_comment(nested),
+            Ok(("d", " a /* b */ c ".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_unterminated_block_comments() {
+        let error = block_comment("/* a /* b */").unwrap_err();
+        assert_eq!(error.location, "/* a /* b */");
+        assert_eq!(error.expected, "closing `*/` of this block comment");
+    }
+
+    #[test]
+    fn reads_doc_comments() {
+        assert_eq!(doc_comment("/// add\n"), Ok(("\n", "add".to_string())));
+        assert!(doc_comment("//// rule").is_err());
+        assert!(doc_comment("// note").is_err());
+    }
+
+    #[test]
+    fn identifiers_follow_uax_31() {
+        assert_eq!(identifier("名前 = 1"), Ok((" = 1", "名前".to_string())));
+        assert_eq!(identifier("$x' y"), Ok((" y", "$x'".to_string())));
+        assert_eq!(identifier("_1"), Ok(("", "_1".to_string())));
+        assert!(identifier("1x").is_err());
+        assert!(identifier("'x").is_err());
+    }
+
+    #[test]
+    fn identifiers_are_normalized() {
+        // `e` and a combining acute accent, which is `é` in NFC
+        assert_eq!(identifier("cafe\u{301}"), Ok(("", "caf\u{e9}".to_string())));
+        assert_eq!(normalize("\u{212b}"), "\u{c5}");
+    }
+
+    #[test]
+    fn keywords_are_not_identifiers() {
+        assert!(identifier("let x").is_err());
+        assert_eq!(keyword("let x"), Ok((" x", "let".to_string())));
+        assert!(keyword("letter").is_err());
+        assert!(keyword("let-x").is_err());
+        assert_eq!(identifier("letter"), Ok(("", "letter".to_string())));
+    }
+
+    #[test]
+    fn warns_about_misleading_identifiers() {
+        assert_eq!(identifier_warning("user名"), None);
+        assert_eq!(identifier_warning("kebab-case"), None);
+        let warning = identifier_warning("p\u{430}ypal").unwrap();
+        assert_eq!(warning, "identifier `p\u{430}ypal` mixes scripts");
+        assert_eq!(skeleton("p\u{430}ypal"), skeleton("paypal"));
+        assert_ne!(skeleton("paypal"), skeleton("payp4l"));
+    }
+
+    #[test]
+    fn hyphens_join_words_by_the_rule() {
+        let between = identifier_with(Hyphen::BetweenLetters);
+        assert_eq!(
+            between("kebab-case x"),
+            Ok((" x", "kebab-case".to_string()))
+        );
+        assert_eq!(between("x-1"), Ok(("-1", "x".to_string())));
+        assert_eq!(between("x-"), Ok(("-", "x".to_string())));
+        let always = identifier_with(Hyphen::Always);
+        assert_eq!(always("x-1"), Ok(("", "x-1".to_string())));
+        assert_eq!(always("x- 1"), Ok(("- 1", "x".to_string())));
+        let never = identifier_with(Hyphen::Never);
+        assert_eq!(never("a-b"), Ok(("-b", "a".to_string())));
+    }
+
+    #[test]
+    fn full_width_forms_alias_ascii() {
+        assert_eq!(ascii_alias('（'), Some('('));
+        assert_eq!(ascii_alias('１'), Some('1'));
+        assert_eq!(ascii_alias('、'), Some(','));
+        assert_eq!(ascii_alias('。'), None);
+        assert_eq!(canonical("ｆ（１，２）"), "f(1,2)");
+        let operators = OperatorRegistry::default();
+        assert_eq!(operators.operator("＝＞ x"), Ok((" x", "=>".to_string())));
+        assert_eq!(operators.operator("＜ 1"), Ok((" 1", "<".to_string())));
+    }
+
+    #[test]
+    fn templates_split_text_and_code() {
+        let segments = vec![Segment::Text("total: ".to_string()), Segment::Code("a + b")];
+        assert_eq!(template("\"total: ${a + b}\""), Ok(("", segments)));
+        let segments = vec![
+            Segment::Text("こんにちは、".to_string()),
+            Segment::Code("name"),
+        ];
+        assert_eq!(template("「こんにちは、${name}」"), Ok(("", segments)));
+        let segments = vec![
+            Segment::Code("f(\"}\", {x})"),
+            Segment::Text(" \\$".to_string()),
+        ];
+        assert_eq!(template("'${f(\"}\", {x})} \\\\\\$'"), Ok(("", segments)));
+        assert_eq!(
+            template_literal("r'${x}'").map(|x| x.1),
+            Ok(vec![Segment::Text("${x}".to_string())])
+        );
+    }
+
+    #[test]
+    fn rejects_unterminated_interpolations() {
+        let e = template("\"a ${b").unwrap_err();
+        let expected = "closing `}` of this interpolation";
+        assert_eq!((e.location, e.expected.as_str()), ("${b", expected));
+        // a quote in the code opens a string of its own
+        let e = template("\"a ${b\"").unwrap_err();
+        assert_eq!(
+            (e.location, e.expected.as_str()),
+            ("\"", "closing `\"` of this string")
+        );
+        let e = string("\"a ${b}\"").unwrap_err();
+        assert_eq!(e.location, "b");
+        assert!(e.expected.contains("escape `$` as `\\$`"));
+    }
+
+    #[test]
+    fn registries_match_the_longest_operator() {
+        let mut operators = OperatorRegistry::default();
+        assert!(operators.insert("<+>"));
+        assert!(!operators.insert("<+>"));
+        assert!(!operators.insert("a+"));
+        assert!(operators.contains("＜＋＞"));
+        assert_eq!(operators.operator("<+>b"), Ok(("b", "<+>".to_string())));
+        assert_eq!(operators.operator("＝＝ 1"), Ok((" 1", "==".to_string())));
+        assert_eq!(operators.operator("<+b"), Ok(("+b", "<".to_string())));
+        assert!(operators.operator("a").is_err());
+        assert_eq!(operators.operator("** 2"), Ok((" 2", "**".to_string())));
+    }
 }
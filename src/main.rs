@@ -1,8 +1,9 @@
 // use compiler::tokenizer;
 
-use crate::compiler::parser::parseco::{self, pieces, Parser};
-
-pub mod compiler;
+use touchme::{
+    character,
+    compiler::parser::parseco::{self, Parser},
+};
 
 fn main() {
     // let parser = compiler::tokenizer::Parser { input: "abc" };
@@ -25,9 +26,6 @@ fn main() {
     println!("{:?}", str_a.asterisk().parse(&mut parseco::state("cab")));
     println!("{:?}", str_a.any().parse(&mut parseco::state("aaab")));
 
-    let ps = parseco::pieces(|x| true);
-    // println!("{:?}", ps.parse(&mut parseco::state("abc")));
-
     println!("{:?}", piece_a_or_x.parse(&mut parseco::state("abc")));
 
     // println!("{:?}", parsec::many(accelerator::primary_expr)("x => x y"));